@@ -1,6 +1,6 @@
 use dotenv::dotenv;
 use rand::{distributions::Alphanumeric, Rng};
-use x_rs::account::Account;
+use x_rs::account::{Account, AuthKey, Endpoints};
 
 #[tokio::main]
 async fn main() {
@@ -14,7 +14,15 @@ async fn main() {
         .map(char::from)
         .collect();
 
-    let mut account = Account::from_file("auth.txt").unwrap();
+    let mut account = match std::env::var("X_AUTH_PASSPHRASE") {
+        Ok(passphrase) => Account::from_encrypted_file(
+            "auth.txt",
+            AuthKey::passphrase(passphrase),
+            Endpoints::default(),
+        ),
+        Err(_) => Account::from_file("auth.txt", Endpoints::default()),
+    }
+    .unwrap();
     account
         .change_password(&old_password, &new_password)
         .await
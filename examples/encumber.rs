@@ -1,6 +1,6 @@
 use dotenv::dotenv;
 use rand::{distributions::Alphanumeric, Rng};
-use x_rs::account::{login, Account};
+use x_rs::account::{login, Account, ClientConfig, Endpoints, LoginCredentials};
 
 #[tokio::main]
 async fn main() {
@@ -12,11 +12,16 @@ async fn main() {
     let email = std::env::var("X_EMAIL").unwrap();
     let totp = std::env::var("X_TOTP").ok();
 
+    let mut credentials =
+        LoginCredentials::username(username, password.as_str()).with_email(email.as_str());
+    if let Some(totp) = totp {
+        credentials = credentials.with_totp_secret(totp).unwrap();
+    }
     let mut login =
-        login::Login::new(username, password.clone(), email.clone(), totp, None).unwrap();
+        login::Login::new(credentials, ClientConfig::default(), Endpoints::default()).unwrap();
     let auth = login.login().await.unwrap();
 
-    let mut account = Account::from_auth(auth).unwrap();
+    let mut account = Account::from_auth(auth, Endpoints::default()).unwrap();
     let new_password: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
         .take(12)
@@ -40,6 +45,10 @@ async fn main() {
             .await
             .unwrap();
     }
+    for phone in account.list_phones().await.unwrap() {
+        log::info!("Removing phone {}", phone.phone_number);
+    }
+    account.remove_all_phones().await.unwrap();
     let phone_email_info = account.get_email_phone_info().await.unwrap();
     assert!(phone_email_info.emails.len() == 1);
     assert!(phone_email_info.emails[0].email == email);
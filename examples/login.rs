@@ -1,5 +1,7 @@
 use dotenv::dotenv;
-use x_rs::account::login;
+use std::path::Path;
+
+use x_rs::account::{login, store, AuthKey, ClientConfig, Endpoints, LoginCredentials};
 
 #[tokio::main]
 async fn main() {
@@ -12,8 +14,21 @@ async fn main() {
     let totp = std::env::var("X_TOTP").ok();
     let proxy_url = std::env::var("PROXY_URL").ok();
 
-    let mut login = login::Login::new(username, password, email, totp, proxy_url).unwrap();
+    let mut credentials = LoginCredentials::username(username, password).with_email(email);
+    if let Some(totp) = totp {
+        credentials = credentials.with_totp_secret(totp).unwrap();
+    }
+    let mut client_config = ClientConfig::new();
+    if let Some(proxy_url) = proxy_url {
+        client_config = client_config.with_proxy(proxy_url);
+    }
+    let mut login = login::Login::new(credentials, client_config, Endpoints::default()).unwrap();
     let auth = login.login().await.unwrap();
-    let auth_json = serde_json::to_string(&auth).unwrap();
-    std::fs::write("auth.txt", auth_json).unwrap();
+    // Set X_AUTH_PASSPHRASE to keep the session encrypted at rest; either
+    // way the file is only readable by its owner.
+    let auth_json = match std::env::var("X_AUTH_PASSPHRASE") {
+        Ok(passphrase) => auth.encrypt(&AuthKey::passphrase(passphrase)).unwrap(),
+        Err(_) => serde_json::to_string(&auth).unwrap(),
+    };
+    store::write_atomic(Path::new("auth.txt"), auth_json.as_bytes()).unwrap();
 }
@@ -1,9 +1,17 @@
-use x_rs::account::Account;
+use x_rs::account::{Account, AuthKey, Endpoints};
 
 #[tokio::main]
 async fn main() {
     env_logger::init();
-    let account = Account::from_file("auth.txt").unwrap();
+    let account = match std::env::var("X_AUTH_PASSPHRASE") {
+        Ok(passphrase) => Account::from_encrypted_file(
+            "auth.txt",
+            AuthKey::passphrase(passphrase),
+            Endpoints::default(),
+        ),
+        Err(_) => Account::from_file("auth.txt", Endpoints::default()),
+    }
+    .unwrap();
     let phone_email_info = account.get_email_phone_info().await.unwrap();
     log::info!("{:?}", phone_email_info);
 }
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use reqwest::Url;
+
+use super::flow::Subtask;
+
+/// Subtask ids X uses for Arkose-backed login challenges.
+pub const ARKOSE_SUBTASKS: [&str; 2] = ["ArkoseLogin", "LoginArkoseChallenge"];
+
+/// An Arkose challenge as X presents it during login.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptchaChallenge {
+    pub subtask_id: String,
+    pub public_key: String,
+    /// The `data` blob Arkose needs to render X's challenge, if X sent one.
+    pub blob: Option<String>,
+    /// The web modal X would open in a browser.
+    pub url: Option<String>,
+}
+
+/// Solves an Arkose challenge and returns the token to submit back to X.
+#[async_trait]
+pub trait CaptchaSolver: Send + Sync {
+    async fn solve(&self, challenge: &CaptchaChallenge) -> eyre::Result<String>;
+}
+
+impl CaptchaChallenge {
+    /// Reads the public key and blob from the subtask, either from explicit
+    /// fields or from the query string of its web modal URL.
+    pub(crate) fn from_subtask(subtask: &Subtask) -> Option<Self> {
+        let raw = &subtask.raw;
+        let url = raw["web_modal"]["url"]
+            .as_str()
+            .or(raw["arkose_challenge"]["url"].as_str())
+            .map(str::to_string);
+        let query: Vec<(String, String)> = url
+            .as_deref()
+            .and_then(|url| Url::parse(url).ok())
+            .map(|url| url.query_pairs().into_owned().collect())
+            .unwrap_or_default();
+        let from_query = |names: &[&str]| {
+            query
+                .iter()
+                .find(|(key, _)| names.contains(&key.as_str()))
+                .map(|(_, value)| value.clone())
+        };
+        let field = |name: &str| {
+            ["arkose_challenge", "web_modal"]
+                .iter()
+                .find_map(|object| raw[object][name].as_str())
+                .map(str::to_string)
+        };
+
+        let public_key = field("public_key").or_else(|| from_query(&["public_key", "pk"]))?;
+        let blob = field("blob")
+            .or_else(|| field("data"))
+            .or_else(|| from_query(&["data", "blob"]));
+        Some(Self {
+            subtask_id: subtask.subtask_id.clone(),
+            public_key,
+            blob,
+            url,
+        })
+    }
+
+    pub(crate) fn response(&self, token: &str) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": self.subtask_id,
+            "web_modal": {
+                "completion_deeplink": format!(
+                    "twitter://onboarding/web_modal/next_link?access_token={}",
+                    token
+                ),
+                "link": "next_link"
+            }
+        })
+    }
+}
@@ -0,0 +1,301 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use fake_user_agent::{get_chrome_rua, get_safari_rua};
+use reqwest::{
+    header::{HeaderMap, HeaderName, ACCEPT_LANGUAGE, AUTHORIZATION, USER_AGENT},
+    Client, Proxy,
+};
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+
+/// The bearer token X's web client is built with.
+const WEB_BEARER_TOKEN: &str = "Bearer AAAAAAAAAAAAAAAAAAAAANRILgAAAAAAnNwIzUejRCOuH5E6I8xnZz4puTs%3D1Zv7ttfk8LF81IUq16cHjhLTvJu4FA33AGWWjCpTnA";
+const CLIENT_LANGUAGE: &str = "x-twitter-client-language";
+const SEC_CH_UA: &str = "sec-ch-ua";
+const SEC_CH_UA_MOBILE: &str = "sec-ch-ua-mobile";
+const SEC_CH_UA_PLATFORM: &str = "sec-ch-ua-platform";
+/// Chrome started sending client hints in version 89.
+const FIRST_CLIENT_HINTS_CHROME: u32 = 89;
+
+/// The browser an account claims to be. Generated once, at login, and
+/// kept with the session: a user agent or language that changes between
+/// requests gives automation away.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClientProfile {
+    user_agent: String,
+    accept_language: String,
+    /// Sent as `x-twitter-client-language`.
+    client_language: String,
+    /// Client hints; only Chromium browsers send them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sec_ch_ua: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sec_ch_ua_mobile: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sec_ch_ua_platform: Option<String>,
+    bearer_token: String,
+}
+
+fn chrome_major_version(user_agent: &str) -> Option<u32> {
+    let version = user_agent.split("Chrome/").nth(1)?;
+    version.split('.').next()?.parse().ok()
+}
+
+fn platform(user_agent: &str) -> &'static str {
+    if user_agent.contains("Android") {
+        "Android"
+    } else if user_agent.contains("Windows") {
+        "Windows"
+    } else if user_agent.contains("Macintosh") || user_agent.contains("Mac OS X") {
+        "macOS"
+    } else if user_agent.contains("CrOS") {
+        "Chrome OS"
+    } else {
+        "Linux"
+    }
+}
+
+impl ClientProfile {
+    /// A desktop Safari or Chrome, with client hints to match the user
+    /// agent.
+    pub fn generate() -> Self {
+        let user_agent = if rand::random::<bool>() {
+            get_chrome_rua()
+        } else {
+            get_safari_rua()
+        };
+        Self::for_user_agent(user_agent)
+    }
+
+    /// Derives the rest of the profile from `user_agent`.
+    pub fn for_user_agent(user_agent: impl Into<String>) -> Self {
+        let user_agent = user_agent.into();
+        let mut profile = Self {
+            accept_language: "en-US,en;q=0.9".to_string(),
+            client_language: "en".to_string(),
+            sec_ch_ua: None,
+            sec_ch_ua_mobile: None,
+            sec_ch_ua_platform: None,
+            bearer_token: WEB_BEARER_TOKEN.to_string(),
+            user_agent,
+        };
+        let is_chromium = !profile.user_agent.contains("Edg/");
+        if let Some(major) = chrome_major_version(&profile.user_agent)
+            .filter(|major| is_chromium && *major >= FIRST_CLIENT_HINTS_CHROME)
+        {
+            profile.sec_ch_ua = Some(format!(
+                "\"Chromium\";v=\"{major}\", \"Google Chrome\";v=\"{major}\", \"Not-A.Brand\";v=\"99\""
+            ));
+            let mobile = profile.user_agent.contains("Mobile");
+            profile.sec_ch_ua_mobile = Some(if mobile { "?1" } else { "?0" }.to_string());
+            profile.sec_ch_ua_platform = Some(format!("\"{}\"", platform(&profile.user_agent)));
+        }
+        profile
+    }
+
+    /// Recovers the profile a session was using from its headers, e.g. for
+    /// auth saved before profiles existed. `None` without a user agent.
+    pub fn from_headers(headers: &HashMap<String, String>) -> Option<Self> {
+        let mut profile = Self::for_user_agent(headers.get(USER_AGENT.as_str())?);
+        profile.sec_ch_ua = headers.get(SEC_CH_UA).cloned();
+        profile.sec_ch_ua_mobile = headers.get(SEC_CH_UA_MOBILE).cloned();
+        profile.sec_ch_ua_platform = headers.get(SEC_CH_UA_PLATFORM).cloned();
+        if let Some(accept_language) = headers.get(ACCEPT_LANGUAGE.as_str()) {
+            profile.accept_language = accept_language.clone();
+        }
+        if let Some(client_language) = headers.get(CLIENT_LANGUAGE) {
+            profile.client_language = client_language.clone();
+        }
+        if let Some(bearer_token) = headers.get(AUTHORIZATION.as_str()) {
+            profile.bearer_token = bearer_token.clone();
+        }
+        Some(profile)
+    }
+
+    /// Sets `Accept-Language` and the language X's UI is served in, e.g.
+    /// `("de-DE,de;q=0.9", "de")`.
+    pub fn with_language(
+        mut self,
+        accept_language: impl Into<String>,
+        client_language: impl Into<String>,
+    ) -> Self {
+        self.accept_language = accept_language.into();
+        self.client_language = client_language.into();
+        self
+    }
+
+    pub fn with_bearer_token(mut self, bearer_token: impl Into<String>) -> Self {
+        self.bearer_token = bearer_token.into();
+        self
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    pub fn accept_language(&self) -> &str {
+        &self.accept_language
+    }
+
+    pub fn client_language(&self) -> &str {
+        &self.client_language
+    }
+
+    pub fn sec_ch_ua(&self) -> Option<&str> {
+        self.sec_ch_ua.as_deref()
+    }
+
+    pub fn sec_ch_ua_mobile(&self) -> Option<&str> {
+        self.sec_ch_ua_mobile.as_deref()
+    }
+
+    pub fn sec_ch_ua_platform(&self) -> Option<&str> {
+        self.sec_ch_ua_platform.as_deref()
+    }
+
+    pub fn bearer_token(&self) -> &str {
+        &self.bearer_token
+    }
+
+    /// Makes `headers` carry this profile, dropping client hints it does
+    /// not have.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) -> eyre::Result<()> {
+        headers.insert(USER_AGENT, self.user_agent.parse()?);
+        headers.insert(ACCEPT_LANGUAGE, self.accept_language.parse()?);
+        headers.insert(CLIENT_LANGUAGE, self.client_language.parse()?);
+        headers.insert(AUTHORIZATION, self.bearer_token.parse()?);
+        for (name, value) in [
+            (SEC_CH_UA, &self.sec_ch_ua),
+            (SEC_CH_UA_MOBILE, &self.sec_ch_ua_mobile),
+            (SEC_CH_UA_PLATFORM, &self.sec_ch_ua_platform),
+        ] {
+            let name = HeaderName::from_static(name);
+            match value {
+                Some(value) => headers.insert(name, value.parse()?),
+                None => headers.remove(name),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// How an account talks to X: which proxy it goes through and which
+/// browser it claims to be. Saved with the session so later requests come
+/// from the same network identity as the login did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClientConfig {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` URL, optionally
+    /// with credentials.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connect_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pool_idle_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pool_max_idle_per_host: Option<usize>,
+    /// Generated at login unless set here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<ClientProfile>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Limits each request, from connecting to reading the whole body.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = Some(millis(timeout));
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout_ms = Some(millis(timeout));
+        self
+    }
+
+    pub fn with_pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout_ms = Some(millis(timeout));
+        self
+    }
+
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    pub fn with_profile(mut self, profile: ClientProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn pool_idle_timeout(&self) -> Option<Duration> {
+        self.pool_idle_timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn pool_max_idle_per_host(&self) -> Option<usize> {
+        self.pool_max_idle_per_host
+    }
+
+    pub fn profile(&self) -> Option<&ClientProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Makes `headers` carry the profile, generating one first if there is
+    /// none yet so it sticks from now on.
+    pub(crate) fn pin_profile(&mut self, headers: &mut HeaderMap) -> eyre::Result<()> {
+        self.profile
+            .get_or_insert_with(ClientProfile::generate)
+            .apply(headers)
+    }
+
+    pub(crate) fn build(
+        &self,
+        cookie_store: Arc<CookieStoreMutex>,
+        headers: HeaderMap,
+    ) -> eyre::Result<Client> {
+        let mut client_builder = Client::builder()
+            .cookie_provider(cookie_store)
+            .default_headers(headers);
+        if let Some(proxy) = &self.proxy {
+            client_builder = client_builder.proxy(Proxy::all(proxy)?);
+        }
+        if let Some(timeout) = self.timeout() {
+            client_builder = client_builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout() {
+            client_builder = client_builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.pool_idle_timeout() {
+            client_builder = client_builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            client_builder = client_builder.pool_max_idle_per_host(max);
+        }
+        Ok(client_builder.build()?)
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cookie_store::{Cookie, CookieDomain, CookieExpiration, CookieStore};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use super::{login, AccountAuth, ClientConfig};
+
+const X_DOMAINS: [&str; 2] = ["x.com", "twitter.com"];
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// A cookie as browser extensions such as EditThisCookie export it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BrowserCookie {
+    domain: String,
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default = "root_path")]
+    path: String,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    session: bool,
+    /// Seconds since the epoch; absent for session cookies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiration_date: Option<f64>,
+    name: String,
+    value: String,
+}
+
+fn root_path() -> String {
+    "/".to_string()
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+impl BrowserCookie {
+    fn is_x(&self) -> bool {
+        let domain = self.domain.trim_start_matches('.');
+        X_DOMAINS
+            .iter()
+            .any(|x| domain == *x || domain.ends_with(&format!(".{x}")))
+    }
+
+    fn from_netscape_line(line: &str) -> Option<Self> {
+        let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(line) => (line, true),
+            None if line.starts_with('#') => return None,
+            None => (line, false),
+        };
+        let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+        let [domain, include_subdomains, path, secure, expires, name, value] = fields[..] else {
+            return None;
+        };
+        let expires: f64 = expires.parse().ok()?;
+        Some(Self {
+            domain: domain.to_string(),
+            host_only: !include_subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            session: expires == 0.0,
+            expiration_date: (expires != 0.0).then_some(expires),
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    fn to_netscape_line(&self) -> String {
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+        format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only { HTTP_ONLY_PREFIX } else { "" },
+            self.domain,
+            flag(!self.host_only),
+            self.path,
+            flag(self.secure),
+            self.expiration_date.map_or(0, |expires| expires as i64),
+            self.name,
+            self.value
+        )
+    }
+
+    fn from_store(cookie: &Cookie<'_>) -> Option<Self> {
+        let (domain, host_only) = match &cookie.domain {
+            CookieDomain::HostOnly(domain) => (domain.clone(), true),
+            CookieDomain::Suffix(domain) => (format!(".{domain}"), false),
+            _ => return None,
+        };
+        let expiration_date = match &cookie.expires {
+            CookieExpiration::AtUtc(expires) => Some(expires.unix_timestamp() as f64),
+            CookieExpiration::SessionEnd => None,
+        };
+        Some(Self {
+            domain,
+            host_only,
+            path: cookie.path.to_string(),
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            session: expiration_date.is_none(),
+            expiration_date,
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+        })
+    }
+
+    /// Replays the cookie as the `Set-Cookie` X would have sent.
+    fn insert_into(&self, cookie_store: &mut CookieStore) -> eyre::Result<()> {
+        let domain = self.domain.trim_start_matches('.');
+        let mut set_cookie = format!("{}={}; Path={}", self.name, self.value, self.path);
+        if !self.host_only {
+            set_cookie.push_str(&format!("; Domain=.{domain}"));
+        }
+        if self.secure {
+            set_cookie.push_str("; Secure");
+        }
+        if self.http_only {
+            set_cookie.push_str("; HttpOnly");
+        }
+        if let Some(expires) = self.expiration_date {
+            let max_age = (expires - now()).ceil() as i64;
+            set_cookie.push_str(&format!("; Max-Age={max_age}"));
+        }
+        let url = Url::parse(&format!("https://{domain}{}", self.path))?;
+        cookie_store.parse(&set_cookie, &url)?;
+        Ok(())
+    }
+}
+
+impl AccountAuth {
+    /// Exports the x.com and twitter.com cookies in the Netscape
+    /// `cookies.txt` format curl and most browser extensions read.
+    pub fn to_netscape(&self) -> eyre::Result<String> {
+        let mut cookies_txt = format!("{NETSCAPE_HEADER}\n");
+        for cookie in self.browser_cookies()? {
+            cookies_txt.push_str(&cookie.to_netscape_line());
+            cookies_txt.push('\n');
+        }
+        Ok(cookies_txt)
+    }
+
+    /// Exports the x.com and twitter.com cookies as the JSON array
+    /// EditThisCookie and similar extensions import.
+    pub fn to_browser_json(&self) -> eyre::Result<String> {
+        Ok(serde_json::to_string_pretty(&self.browser_cookies()?)?)
+    }
+
+    /// Builds a session from a Netscape `cookies.txt` export. Only x.com and
+    /// twitter.com cookies are kept; session cookies are dropped since
+    /// `AccountAuth` only stores persistent ones.
+    pub fn from_netscape(cookies_txt: &str) -> eyre::Result<Self> {
+        let cookies = cookies_txt
+            .lines()
+            .filter_map(BrowserCookie::from_netscape_line);
+        Self::from_browser_cookies(cookies)
+    }
+
+    /// Builds a session from an EditThisCookie style JSON export, with the
+    /// same filtering as [`AccountAuth::from_netscape`].
+    pub fn from_browser_json(json: &str) -> eyre::Result<Self> {
+        let cookies: Vec<BrowserCookie> = serde_json::from_str(json)?;
+        Self::from_browser_cookies(cookies)
+    }
+
+    fn browser_cookies(&self) -> eyre::Result<Vec<BrowserCookie>> {
+        let cookie_store = self.cookie_store()?;
+        Ok(cookie_store
+            .iter_unexpired()
+            .filter_map(BrowserCookie::from_store)
+            .filter(BrowserCookie::is_x)
+            .collect())
+    }
+
+    fn from_browser_cookies(
+        cookies: impl IntoIterator<Item = BrowserCookie>,
+    ) -> eyre::Result<Self> {
+        let now = now();
+        let mut cookie_store = CookieStore::default();
+        let mut ct0 = None;
+        for cookie in cookies {
+            let live = cookie.expiration_date.is_some_and(|expires| expires > now);
+            if !cookie.is_x() || !live {
+                continue;
+            }
+            if cookie.name == "ct0" {
+                ct0 = Some(cookie.value.clone());
+            }
+            cookie.insert_into(&mut cookie_store)?;
+        }
+        let mut headers = login::default_headers()?;
+        headers.insert("x-twitter-auth-type", "OAuth2Session".parse()?);
+        if let Some(ct0) = ct0 {
+            headers.insert("x-csrf-token", ct0.parse()?);
+        }
+        let mut client = ClientConfig::default();
+        client.pin_profile(&mut headers)?;
+        Ok(Self::new(headers, cookie_store).with_client_config(client))
+    }
+}
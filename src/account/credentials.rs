@@ -0,0 +1,171 @@
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+use super::{totp::TotpConfig, LoginError};
+
+/// A string that is wiped from memory when dropped and never printed by
+/// `Debug`.
+#[derive(Clone)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(Zeroizing::new(secret.into()))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentifierKind {
+    Username,
+    Email,
+    Phone,
+}
+
+/// Who is logging in and how they prove it. The identifier the credentials
+/// were built with is typed into X's first prompt; the others only answer
+/// `LoginEnterAlternateIdentifierSubtask`.
+#[derive(Debug, Clone)]
+pub struct LoginCredentials {
+    kind: IdentifierKind,
+    username: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    password: SecretString,
+    totp: Option<TotpConfig>,
+}
+
+impl LoginCredentials {
+    pub fn username(username: impl Into<String>, password: impl Into<SecretString>) -> Self {
+        Self::new(IdentifierKind::Username, password).with_username(username)
+    }
+
+    pub fn email(email: impl Into<String>, password: impl Into<SecretString>) -> Self {
+        Self::new(IdentifierKind::Email, password).with_email(email)
+    }
+
+    pub fn phone(phone: impl Into<String>, password: impl Into<SecretString>) -> Self {
+        Self::new(IdentifierKind::Phone, password).with_phone(phone)
+    }
+
+    fn new(kind: IdentifierKind, password: impl Into<SecretString>) -> Self {
+        Self {
+            kind,
+            username: None,
+            email: None,
+            phone: None,
+            password: password.into(),
+            totp: None,
+        }
+    }
+
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn with_email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    pub fn with_phone(mut self, phone: impl Into<String>) -> Self {
+        self.phone = Some(phone.into());
+        self
+    }
+
+    /// Accepts a base32 secret or an `otpauth://` URI, see [`TotpConfig::new`].
+    pub fn with_totp_secret(self, secret: impl Into<SecretString>) -> Result<Self, LoginError> {
+        let config = TotpConfig::new(secret.into().expose())?;
+        Ok(self.with_totp(config))
+    }
+
+    pub fn with_totp(mut self, config: TotpConfig) -> Self {
+        self.totp = Some(config);
+        self
+    }
+
+    pub fn kind(&self) -> IdentifierKind {
+        self.kind
+    }
+
+    /// The identifier typed into X's first login prompt.
+    pub fn identifier(&self) -> &str {
+        self.get(self.kind).unwrap_or_default()
+    }
+
+    pub fn get(&self, kind: IdentifierKind) -> Option<&str> {
+        match kind {
+            IdentifierKind::Username => self.username.as_deref(),
+            IdentifierKind::Email => self.email.as_deref(),
+            IdentifierKind::Phone => self.phone.as_deref(),
+        }
+    }
+
+    pub fn password(&self) -> &SecretString {
+        &self.password
+    }
+
+    pub fn totp(&self) -> Option<&TotpConfig> {
+        self.totp.as_ref()
+    }
+
+    /// Picks what to answer `LoginEnterAlternateIdentifierSubtask` with from
+    /// X's prompt, e.g. "Enter your phone number or username". Identifiers
+    /// other than the one already typed in are preferred, since X asks for
+    /// something it has not seen yet.
+    pub(crate) fn alternate_identifier(&self, prompt: &str) -> Option<&str> {
+        let prompt = prompt.to_lowercase();
+        let asked_for = |kind: &IdentifierKind| match kind {
+            IdentifierKind::Username => prompt.contains("username"),
+            IdentifierKind::Email => prompt.contains("email"),
+            IdentifierKind::Phone => prompt.contains("phone"),
+        };
+        let kinds = [
+            IdentifierKind::Email,
+            IdentifierKind::Phone,
+            IdentifierKind::Username,
+        ];
+        let mut candidates: Vec<IdentifierKind> = kinds
+            .iter()
+            .filter(|kind| **kind != self.kind && asked_for(kind))
+            .chain(
+                kinds
+                    .iter()
+                    .filter(|kind| **kind == self.kind && asked_for(kind)),
+            )
+            .copied()
+            .collect();
+        if candidates.is_empty() {
+            // Nothing recognisable in the prompt; fall back to the email,
+            // which is what X asks for most of the time.
+            candidates = kinds
+                .into_iter()
+                .filter(|kind| *kind != self.kind)
+                .collect();
+        }
+        candidates.into_iter().find_map(|kind| self.get(kind))
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{
+    flow::{FlowContext, FlowStatus, OnboardingFlow, Subtask, SubtaskHandler, SubtaskOutcome},
+    verification::EmailCodeProvider,
+    Account, LoginError,
+};
+
+const ADD_EMAIL_FLOW: &str = "add_email";
+const ENTER_PASSWORD: &str = "EnterPassword";
+const ENTER_EMAIL: &str = "EmailAssocEnterEmail";
+const VERIFY_EMAIL: &str = "EmailVerification";
+
+/// Answers the `add_email` flow's password and address subtasks, and pauses
+/// on the code so it can be fetched once X has mailed it.
+struct AddEmailSubtasks {
+    new_email: String,
+    password: String,
+}
+
+#[async_trait]
+impl SubtaskHandler for AddEmailSubtasks {
+    async fn handle(
+        &self,
+        subtask: &Subtask,
+        _context: &mut FlowContext,
+    ) -> Result<SubtaskOutcome, LoginError> {
+        let input = match subtask.subtask_id.as_str() {
+            ENTER_PASSWORD => serde_json::json!({
+                "subtask_id": ENTER_PASSWORD,
+                "enter_password": {
+                    "password": self.password,
+                    "link": "next_link"
+                },
+            }),
+            ENTER_EMAIL => serde_json::json!({
+                "subtask_id": ENTER_EMAIL,
+                "enter_text": {
+                    "text": self.new_email,
+                    "link": "next_link"
+                },
+            }),
+            _ => return Ok(SubtaskOutcome::Pause),
+        };
+        Ok(SubtaskOutcome::Submit(vec![input]))
+    }
+}
+
+impl Account {
+    /// Replaces the account's email with `new_email` through X's `add_email`
+    /// flow, confirmed with the code X mails there. Fails unless afterwards
+    /// `new_email` is the only address left.
+    pub async fn change_email(
+        &self,
+        new_email: &str,
+        password: &str,
+        code_provider: &dyn EmailCodeProvider,
+    ) -> eyre::Result<()> {
+        let mut flow = OnboardingFlow::new(
+            self.client.clone(),
+            self.headers.clone(),
+            self.cookie_store.clone(),
+            self.endpoints.clone(),
+        );
+        flow.context_mut().sync_csrf_token()?;
+        let handler = Arc::new(AddEmailSubtasks {
+            new_email: new_email.to_string(),
+            password: password.to_string(),
+        });
+        for subtask_id in [ENTER_PASSWORD, ENTER_EMAIL, VERIFY_EMAIL] {
+            flow.register(subtask_id, handler.clone());
+        }
+        let input_flow_data = serde_json::json!({
+            "flow_context": {
+                "debug_overrides": {},
+                "start_location": {
+                    "location": "settings"
+                }
+            },
+        });
+        let status = async {
+            let res = flow.start(ADD_EMAIL_FLOW, input_flow_data).await?;
+            flow.run(res).await
+        }
+        .await
+        .map_err(|e| eyre::Report::new(e).wrap_err("Email change failed"))?;
+        let FlowStatus::Paused(subtask) = status else {
+            eyre::bail!("X did not ask for a confirmation code");
+        };
+
+        let code = code_provider.email_code(new_email).await?;
+        let input = serde_json::json!({
+            "subtask_id": subtask.subtask_id,
+            "email_verification": {
+                "code": code,
+                "email": new_email,
+                "link": "next_link"
+            },
+        });
+        let status = async {
+            let res = flow.submit(vec![input]).await?;
+            flow.run(res).await
+        }
+        .await
+        .map_err(|e| eyre::Report::new(e).wrap_err("Email verification failed"))?;
+        if let FlowStatus::Paused(subtask) = status {
+            eyre::bail!("X asked for {} after the code", subtask.subtask_id);
+        }
+        // The flow's requests bypass `send`, which saves rotated cookies.
+        if let Err(e) = self.save_cookies().await {
+            log::warn!("could not save rotated cookies: {e:#}");
+        }
+
+        let emails = self.get_email_phone_info().await?.emails;
+        if !emails
+            .iter()
+            .any(|email| email.email.eq_ignore_ascii_case(new_email) && email.email_verified)
+        {
+            eyre::bail!("{new_email} is not verified after confirming it");
+        }
+        let others: Vec<_> = emails
+            .iter()
+            .filter(|email| !email.email.eq_ignore_ascii_case(new_email))
+            .map(|email| email.email.as_str())
+            .collect();
+        if !others.is_empty() {
+            eyre::bail!("still attached after the change: {}", others.join(", "));
+        }
+        Ok(())
+    }
+}
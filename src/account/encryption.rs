@@ -0,0 +1,183 @@
+use std::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{credentials::SecretString, AccountAuth};
+
+const FORMAT: &str = "x-rs-encrypted-auth";
+const FORMAT_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+
+/// The key an [`AccountAuth`] is encrypted with: either a passphrase,
+/// stretched with Argon2id, or a raw 256-bit key, e.g. from a KMS.
+#[derive(Clone)]
+pub enum AuthKey {
+    Passphrase(SecretString),
+    Raw(Zeroizing<[u8; 32]>),
+}
+
+impl AuthKey {
+    pub fn passphrase(passphrase: impl Into<SecretString>) -> Self {
+        Self::Passphrase(passphrase.into())
+    }
+
+    pub fn raw(key: [u8; 32]) -> Self {
+        Self::Raw(Zeroizing::new(key))
+    }
+}
+
+impl fmt::Debug for AuthKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passphrase(_) => f.write_str("AuthKey::Passphrase(<redacted>)"),
+            Self::Raw(_) => f.write_str("AuthKey::Raw(<redacted>)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct KdfParams {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// What an encrypted auth file holds. The KDF settings travel with the
+/// file so they can be raised later without breaking old files.
+#[derive(Debug, Deserialize, Serialize)]
+struct EncryptedAuth {
+    format: String,
+    version: u32,
+    /// Present when the key is a passphrase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    argon2id: Option<KdfParams>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(
+    key: &AuthKey,
+    params: Option<KdfParams>,
+    salt: &[u8],
+) -> eyre::Result<Zeroizing<[u8; 32]>> {
+    match key {
+        AuthKey::Raw(key) => Ok(key.clone()),
+        AuthKey::Passphrase(passphrase) => {
+            let params =
+                params.ok_or_else(|| eyre::eyre!("file was not encrypted with a passphrase"))?;
+            let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+                .map_err(|e| eyre::eyre!("invalid Argon2 parameters: {e}"))?;
+            let mut derived = Zeroizing::new([0u8; 32]);
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(passphrase.expose().as_bytes(), salt, derived.as_mut())
+                .map_err(|e| eyre::eyre!("key derivation failed: {e}"))?;
+            Ok(derived)
+        }
+    }
+}
+
+fn associated_data() -> Vec<u8> {
+    format!("{FORMAT}/{FORMAT_VERSION}").into_bytes()
+}
+
+impl AccountAuth {
+    /// Serializes and encrypts the session with XChaCha20-Poly1305.
+    pub fn encrypt(&self, key: &AuthKey) -> eyre::Result<String> {
+        let (argon2id, salt) = match key {
+            AuthKey::Passphrase(_) => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                (Some(KdfParams::default()), salt.to_vec())
+            }
+            AuthKey::Raw(_) => (None, Vec::new()),
+        };
+        let derived = derive_key(key, argon2id, &salt)?;
+        let cipher = XChaCha20Poly1305::new(derived.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = Zeroizing::new(serde_json::to_vec(self)?);
+        let aad = associated_data();
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| eyre::eyre!("encryption failed"))?;
+        let encrypted = EncryptedAuth {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            argon2id,
+            salt: hex_encode(&salt),
+            nonce: hex_encode(&nonce),
+            ciphertext: hex_encode(&ciphertext),
+        };
+        Ok(serde_json::to_string(&encrypted)?)
+    }
+
+    /// Reverses [`AccountAuth::encrypt`]. A wrong key and a tampered file
+    /// fail the same way.
+    pub fn decrypt(encrypted: &str, key: &AuthKey) -> eyre::Result<Self> {
+        let encrypted: EncryptedAuth = serde_json::from_str(encrypted)?;
+        if encrypted.format != FORMAT || encrypted.version != FORMAT_VERSION {
+            eyre::bail!(
+                "unsupported encrypted auth format {} v{}",
+                encrypted.format,
+                encrypted.version
+            );
+        }
+        let salt = hex_decode(&encrypted.salt)?;
+        let nonce = hex_decode(&encrypted.nonce)?;
+        if nonce.len() != 24 {
+            eyre::bail!("bad nonce length {}", nonce.len());
+        }
+        let derived = derive_key(key, encrypted.argon2id, &salt)?;
+        let cipher = XChaCha20Poly1305::new(derived.as_ref().into());
+        let aad = associated_data();
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &hex_decode(&encrypted.ciphertext)?,
+                    aad: &aad,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| eyre::eyre!("could not decrypt auth: wrong key or corrupted file"))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(hex: &str) -> eyre::Result<Vec<u8>> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        eyre::bail!("malformed hex string");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&hex[i..i + 2], 16)?))
+        .collect()
+}
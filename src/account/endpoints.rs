@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+const API_BASE: &str = "https://api.x.com";
+const WEB_BASE: &str = "https://x.com";
+
+/// Base URLs for the two hosts the crate talks to. Point both at a local
+/// server to run login and account calls offline.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Endpoints {
+    pub api_base: String,
+    pub web_base: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self::new(API_BASE, WEB_BASE)
+    }
+}
+
+impl Endpoints {
+    pub fn new(api_base: impl Into<String>, web_base: impl Into<String>) -> Self {
+        Self {
+            api_base: api_base.into().trim_end_matches('/').to_string(),
+            web_base: web_base.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Routes both `api.x.com` and `x.com` traffic to a single base URL.
+    pub fn local(base: impl Into<String>) -> Self {
+        let base = base.into();
+        Self::new(base.clone(), base)
+    }
+
+    pub(crate) fn api(&self, path: &str) -> String {
+        format!("{}{}", self.api_base, path)
+    }
+
+    pub(crate) fn web(&self, path: &str) -> String {
+        format!("{}{}", self.web_base, path)
+    }
+}
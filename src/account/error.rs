@@ -0,0 +1,147 @@
+use reqwest::StatusCode;
+
+/// Error codes X documents for `{"errors": [{"code": .., "message": ..}]}`
+/// payloads that callers commonly need to act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XErrorCode {
+    BadAuthentication,
+    Suspended,
+    RateLimited,
+    InvalidToken,
+    Locked,
+    CsrfMismatch,
+    SuspiciousLogin,
+    Other(u32),
+}
+
+impl From<u32> for XErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            32 => Self::BadAuthentication,
+            64 => Self::Suspended,
+            88 => Self::RateLimited,
+            89 => Self::InvalidToken,
+            326 => Self::Locked,
+            353 => Self::CsrfMismatch,
+            399 => Self::SuspiciousLogin,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl XErrorCode {
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::BadAuthentication => 32,
+            Self::Suspended => 64,
+            Self::RateLimited => 88,
+            Self::InvalidToken => 89,
+            Self::Locked => 326,
+            Self::CsrfMismatch => 353,
+            Self::SuspiciousLogin => 399,
+            Self::Other(code) => *code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("X API error {status} (code {:?}): {message}", code.map(|c| c.code()))]
+pub struct XApiError {
+    pub status: u16,
+    /// The first error code in the payload, if X sent one.
+    pub code: Option<XErrorCode>,
+    pub message: String,
+    /// Epoch seconds from `x-rate-limit-reset`, when present.
+    pub rate_limit_reset: Option<u64>,
+}
+
+impl XApiError {
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+            || self.code == Some(XErrorCode::RateLimited)
+    }
+
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(
+            self.code,
+            Some(XErrorCode::BadAuthentication | XErrorCode::InvalidToken)
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoginError {
+    #[error("wrong password")]
+    WrongPassword,
+    #[error("MFA code is required")]
+    TotpRequired,
+    #[error("SMS verification code is required")]
+    SmsCodeRequired,
+    #[error("backup code is required")]
+    BackupCodeRequired,
+    #[error("email verification code is required")]
+    EmailCodeRequired,
+    #[error("invalid TOTP secret: {reason}")]
+    InvalidTotpSecret { reason: String },
+    #[error("captcha solver is required")]
+    CaptchaRequired,
+    #[error("no identifier to answer \"{prompt}\"")]
+    IdentifierRequired { prompt: String },
+    #[error("login denied: {message}")]
+    Denied { message: String },
+    #[error("account is suspended")]
+    Suspended,
+    #[error("account is locked")]
+    Locked,
+    #[error("logged in as @{actual} instead of {expected}")]
+    WrongUser { expected: String, actual: String },
+    #[error("rate limited (reset at {reset:?})")]
+    RateLimited { reset: Option<u64> },
+    #[error("unknown login subtask {id}")]
+    UnknownSubtask { id: String, raw: serde_json::Value },
+    /// A subtask handler paused the login on a subtask `login` cannot
+    /// answer by itself; use [`Login::start`](super::login::Login::start).
+    #[error("login subtask {id} needs input")]
+    InputRequired { id: String, raw: serde_json::Value },
+    #[error(transparent)]
+    Api(XApiError),
+    #[error(transparent)]
+    Transport(#[from] reqwest::Error),
+    #[error(transparent)]
+    Other(#[from] eyre::Report),
+}
+
+impl From<XApiError> for LoginError {
+    fn from(error: XApiError) -> Self {
+        if error.is_rate_limited() {
+            return Self::RateLimited {
+                reset: error.rate_limit_reset,
+            };
+        }
+        match error.code {
+            Some(XErrorCode::Suspended) => Self::Suspended,
+            Some(XErrorCode::Locked) => Self::Locked,
+            Some(XErrorCode::SuspiciousLogin) => Self::Denied {
+                message: error.message,
+            },
+            _ => Self::Api(error),
+        }
+    }
+}
+
+impl LoginError {
+    /// Maps X rejecting the answer to `subtask_id`: a rejected password is
+    /// a wrong password, whatever X's message says.
+    pub(crate) fn rejected(subtask_id: Option<&str>, error: XApiError) -> Self {
+        match (subtask_id, error.code) {
+            (Some("LoginEnterPassword"), Some(XErrorCode::SuspiciousLogin)) => Self::WrongPassword,
+            _ => error.into(),
+        }
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for LoginError {
+    fn from(error: reqwest::header::InvalidHeaderValue) -> Self {
+        Self::Other(error.into())
+    }
+}
@@ -0,0 +1,267 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use eyre::OptionExt;
+use reqwest::{header::HeaderMap, Client};
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::Deserialize;
+
+use super::{response, Endpoints, LoginError};
+
+const GUEST_ACTIVATE_PATH: &str = "/1.1/guest/activate.json";
+const TASK_PATH: &str = "/1.1/onboarding/task.json";
+
+#[derive(Deserialize, Debug)]
+pub struct TaskResponse {
+    pub flow_token: String,
+    pub subtasks: Vec<Subtask>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Subtask {
+    pub subtask_id: String,
+    /// The subtask exactly as X sent it, prompts and all.
+    pub raw: serde_json::Value,
+}
+
+impl<'de> Deserialize<'de> for Subtask {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = serde_json::Value::deserialize(deserializer)?;
+        let subtask_id = raw["subtask_id"]
+            .as_str()
+            .ok_or_else(|| serde::de::Error::missing_field("subtask_id"))?
+            .to_string();
+        Ok(Self { subtask_id, raw })
+    }
+}
+
+/// What the runner should do with a handled subtask.
+#[derive(Debug)]
+pub enum SubtaskOutcome {
+    /// Post these `subtask_inputs` and dispatch whatever X returns next.
+    Submit(Vec<serde_json::Value>),
+    /// Post these `subtask_inputs` and end the flow.
+    Finish(Vec<serde_json::Value>),
+    /// Stop and hand the subtask back to the caller, which answers it with
+    /// [`OnboardingFlow::submit`] once it has the input.
+    Pause,
+}
+
+#[derive(Debug)]
+pub enum FlowStatus {
+    Complete(TaskResponse),
+    Paused(Subtask),
+}
+
+/// Answers one kind of subtask in an onboarding flow.
+#[async_trait]
+pub trait SubtaskHandler: Send + Sync {
+    async fn handle(
+        &self,
+        subtask: &Subtask,
+        context: &mut FlowContext,
+    ) -> Result<SubtaskOutcome, LoginError>;
+}
+
+/// The request state a flow carries between subtasks. Handlers may adjust
+/// headers, e.g. to pick up a freshly issued `ct0`.
+pub struct FlowContext {
+    pub headers: HeaderMap,
+    cookie_store: Arc<CookieStoreMutex>,
+    clock_offset: i64,
+}
+
+impl FlowContext {
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        let cookie_store = self.cookie_store.lock().unwrap();
+        let value = cookie_store
+            .iter_unexpired()
+            .find(|cookie| cookie.name() == name)
+            .map(|cookie| cookie.value().to_string());
+        value
+    }
+
+    /// Seconds X's clock was ahead of ours on the last response.
+    pub fn clock_offset(&self) -> i64 {
+        self.clock_offset
+    }
+
+    pub fn sync_csrf_token(&mut self) -> Result<(), LoginError> {
+        let ct0_cookie = self.cookie("ct0").ok_or_eyre("ct0 cookie not found")?;
+        self.headers.insert("x-csrf-token", ct0_cookie.parse()?);
+        Ok(())
+    }
+}
+
+/// Drives `/1.1/onboarding/task.json`: posts answers, then hands each
+/// returned subtask to the handler registered for its id.
+pub struct OnboardingFlow {
+    client: Client,
+    endpoints: Endpoints,
+    context: FlowContext,
+    flow_token: Option<String>,
+    handlers: HashMap<String, Arc<dyn SubtaskHandler>>,
+}
+
+impl OnboardingFlow {
+    pub fn new(
+        client: Client,
+        headers: HeaderMap,
+        cookie_store: Arc<CookieStoreMutex>,
+        endpoints: Endpoints,
+    ) -> Self {
+        Self {
+            client,
+            endpoints,
+            context: FlowContext {
+                headers,
+                cookie_store,
+                clock_offset: 0,
+            },
+            flow_token: None,
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, subtask_id: &str, handler: Arc<dyn SubtaskHandler>) {
+        self.handlers.insert(subtask_id.to_string(), handler);
+    }
+
+    /// Registers `handler` unless one is already registered for the id.
+    pub fn register_default(&mut self, subtask_id: &str, handler: Arc<dyn SubtaskHandler>) {
+        self.handlers
+            .entry(subtask_id.to_string())
+            .or_insert(handler);
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    pub fn context(&self) -> &FlowContext {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut FlowContext {
+        &mut self.context
+    }
+
+    pub fn flow_token(&self) -> Option<&str> {
+        self.flow_token.as_deref()
+    }
+
+    pub fn set_flow_token(&mut self, flow_token: String) {
+        self.flow_token = Some(flow_token);
+    }
+
+    pub async fn activate_guest(&mut self) -> Result<(), LoginError> {
+        let response = self
+            .client
+            .post(self.endpoints.api(GUEST_ACTIVATE_PATH))
+            .headers(self.context.headers.clone())
+            .send()
+            .await?;
+        let response = self.read(response).await?;
+        let value: serde_json::Value = response.json()?;
+        let guest_token = value["guest_token"]
+            .as_str()
+            .ok_or_eyre("guest_token missing from response")?;
+        self.context
+            .headers
+            .insert("x-guest-token", guest_token.parse()?);
+        Ok(())
+    }
+
+    pub async fn start(
+        &mut self,
+        flow_name: &str,
+        input_flow_data: serde_json::Value,
+    ) -> Result<TaskResponse, LoginError> {
+        let payload = serde_json::json!({ "input_flow_data": input_flow_data });
+        let response = self
+            .client
+            .post(self.endpoints.api(TASK_PATH))
+            .headers(self.context.headers.clone())
+            .query(&[("flow_name", flow_name)])
+            .json(&payload)
+            .send()
+            .await?;
+        self.read_task(response, None).await
+    }
+
+    pub async fn submit(
+        &mut self,
+        subtask_inputs: Vec<serde_json::Value>,
+    ) -> Result<TaskResponse, LoginError> {
+        let answered = subtask_inputs
+            .first()
+            .and_then(|input| input["subtask_id"].as_str())
+            .map(str::to_string);
+        let payload = serde_json::json!({
+            "flow_token": self.flow_token,
+            "subtask_inputs": subtask_inputs,
+        });
+        let response = self
+            .client
+            .post(self.endpoints.api(TASK_PATH))
+            .headers(self.context.headers.clone())
+            .json(&payload)
+            .send()
+            .await?;
+        self.read_task(response, answered.as_deref()).await
+    }
+
+    /// Dispatches subtasks until X returns none, a handler finishes the flow
+    /// or a handler pauses it. Subtasks without a handler fail with
+    /// [`LoginError::UnknownSubtask`].
+    pub async fn run(&mut self, mut res: TaskResponse) -> Result<FlowStatus, LoginError> {
+        loop {
+            let Some(subtask) = res.subtasks.first() else {
+                return Ok(FlowStatus::Complete(res));
+            };
+            let Some(handler) = self.handlers.get(&subtask.subtask_id).cloned() else {
+                let subtask = res.subtasks.swap_remove(0);
+                return Err(LoginError::UnknownSubtask {
+                    id: subtask.subtask_id,
+                    raw: subtask.raw,
+                });
+            };
+            match handler.handle(subtask, &mut self.context).await? {
+                SubtaskOutcome::Submit(inputs) => res = self.submit(inputs).await?,
+                SubtaskOutcome::Finish(inputs) => {
+                    return Ok(FlowStatus::Complete(self.submit(inputs).await?))
+                }
+                SubtaskOutcome::Pause => {
+                    return Ok(FlowStatus::Paused(res.subtasks.swap_remove(0)))
+                }
+            }
+        }
+    }
+
+    async fn read(
+        &mut self,
+        response: reqwest::Response,
+    ) -> Result<response::ApiResponse, LoginError> {
+        let response = response::read(response).await?;
+        if let Some(clock_offset) = response.clock_offset() {
+            self.context.clock_offset = clock_offset;
+        }
+        Ok(response)
+    }
+
+    /// `answered` is the subtask the request answered, which tells what X
+    /// is rejecting if it fails.
+    async fn read_task(
+        &mut self,
+        response: reqwest::Response,
+        answered: Option<&str>,
+    ) -> Result<TaskResponse, LoginError> {
+        let task_response: TaskResponse = self
+            .read(response)
+            .await?
+            .json()
+            .map_err(|error| LoginError::rejected(answered, error))?;
+        self.flow_token = Some(task_response.flow_token.clone());
+        Ok(task_response)
+    }
+}
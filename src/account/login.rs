@@ -1,363 +1,758 @@
 use std::{collections::HashMap, sync::Arc};
 
-use eyre::OptionExt;
-use fake_user_agent::get_safari_rua;
-use reqwest::{header::HeaderMap, Client, Proxy};
-use reqwest_cookie_store::CookieStoreMutex;
-use serde::Deserialize;
-use totp_rs::{Algorithm, Secret, TOTP};
-
-use super::AccountAuth;
-
-const LOGIN_URL: &str = "https://api.x.com/1.1/onboarding/task.json";
-const TOKEN: &str = "Bearer AAAAAAAAAAAAAAAAAAAAANRILgAAAAAAnNwIzUejRCOuH5E6I8xnZz4puTs%3D1Zv7ttfk8LF81IUq16cHjhLTvJu4FA33AGWWjCpTnA";
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    captcha::{CaptchaChallenge, CaptchaSolver, ARKOSE_SUBTASKS},
+    client::{ClientConfig, ClientProfile},
+    credentials::{IdentifierKind, LoginCredentials},
+    flow::{FlowContext, FlowStatus, OnboardingFlow, Subtask, SubtaskHandler, SubtaskOutcome},
+    header_map_from_strings, header_map_to_strings,
+    totp::{TotpConfig, TotpGenerator},
+    verification::{BackupCodes, EmailCodeProvider, SmsCodeProvider},
+    Account, AccountAuth, Endpoints, LoginError, XApiError,
+};
+
+const LOGIN_SUBTASKS: [&str; 9] = [
+    "LoginJsInstrumentationSubtask",
+    "LoginEnterUserIdentifierSSO",
+    "LoginEnterPassword",
+    "LoginTwoFactorAuthChallenge",
+    "LoginTwoFactorAuthChooseMethod",
+    "LoginEnterAlternateIdentifierSubtask",
+    "LoginAcid",
+    "LoginSuccessSubtask",
+    "DenyLoginSubtask",
+];
+
+/// The headers X's web client sends before it has a session, besides the
+/// ones that come from its [`ClientProfile`](super::ClientProfile).
+pub(crate) fn default_headers() -> eyre::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(reqwest::header::CONTENT_TYPE, "application/json".parse()?);
+    headers.insert("x-twitter-active-user", "yes".parse()?);
+    Ok(headers)
+}
 
 pub struct Login {
-    client: Client,
-    headers: HeaderMap,
+    flow: OnboardingFlow,
     cookie_store: Arc<CookieStoreMutex>,
-    username: String,
-    password: String,
-    email: String,
-    totp_code: Option<String>,
+    credentials: LoginCredentials,
+    client_config: ClientConfig,
+    totp: Option<Arc<TotpGenerator>>,
+    backup_codes: Option<Arc<BackupCodes>>,
+    sms_code_provider: Option<Arc<dyn SmsCodeProvider>>,
+    email_code_provider: Option<Arc<dyn EmailCodeProvider>>,
+    captcha_solver: Option<Arc<dyn CaptchaSolver>>,
 }
 
-#[derive(Deserialize, Debug)]
-struct TaskResponse {
-    flow_token: String,
-    subtasks: Vec<Subtask>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TwoFactorMethod {
+    Totp,
+    Sms,
+    BackupCode,
 }
 
-#[derive(Deserialize, Debug)]
-struct Subtask {
-    subtask_id: String,
+impl TwoFactorMethod {
+    // X only names the method in the prompt and choice labels. Authenticator
+    // app prompts may mention the phone the app runs on, so only the words
+    // X uses for the other methods count.
+    fn from_text(text: &str) -> Self {
+        let text = text.to_lowercase();
+        if text.contains("backup code") {
+            Self::BackupCode
+        } else if text.contains("text message") || text.contains("sms") {
+            Self::Sms
+        } else {
+            Self::Totp
+        }
+    }
+
+    /// The error for a login that stopped at this method for want of a code.
+    fn required(self) -> LoginError {
+        match self {
+            Self::Totp => LoginError::TotpRequired,
+            Self::Sms => LoginError::SmsCodeRequired,
+            Self::BackupCode => LoginError::BackupCodeRequired,
+        }
+    }
+}
+
+impl Subtask {
+    // LoginAcid either asks to retype the account email or for the code X
+    // just mailed to it; only the hint text tells them apart.
+    fn asks_for_code(&self) -> bool {
+        self.raw["enter_text"]["hint_text"]
+            .as_str()
+            .is_some_and(|hint| hint.to_lowercase().contains("code"))
+    }
+
+    /// Everything X wrote on an `enter_text` prompt.
+    fn prompt_text(&self) -> String {
+        let enter_text = &self.raw["enter_text"];
+        [
+            &enter_text["primary_text"]["text"],
+            &enter_text["secondary_text"]["text"],
+            &enter_text["hint_text"],
+        ]
+        .iter()
+        .filter_map(|text| text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+
+    fn two_factor_method(&self) -> TwoFactorMethod {
+        TwoFactorMethod::from_text(&self.prompt_text())
+    }
+
+    fn input_required(self) -> LoginError {
+        LoginError::InputRequired {
+            id: self.subtask_id,
+            raw: self.raw,
+        }
+    }
+
+    /// The methods a `LoginTwoFactorAuthChooseMethod` offers, in X's order.
+    fn offered_two_factor_methods(&self) -> Vec<TwoFactorMethod> {
+        self.raw["choice_selection"]["choices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|choice| choice["text"]["text"].as_str())
+            .map(TwoFactorMethod::from_text)
+            .collect()
+    }
+
+    fn two_factor_prompt(&self) -> &str {
+        self.raw["enter_text"]["secondary_text"]["text"]
+            .as_str()
+            .unwrap_or_default()
+    }
+
+    /// The link X offers on a two-factor challenge to pick another method.
+    fn choose_method_link(&self) -> Option<&str> {
+        self.raw["enter_text"]["skip_link"]["link_id"].as_str()
+    }
+
+    fn deny_message(&self) -> String {
+        let cta = &self.raw["cta"];
+        cta["secondary_text"]["text"]
+            .as_str()
+            .or(cta["primary_text"]["text"].as_str())
+            .unwrap_or("login denied by X")
+            .to_string()
+    }
 }
 
 impl Login {
     pub fn new(
-        username: String,
-        password: String,
-        email: String,
-        totp_code: Option<String>,
-        proxy: Option<String>,
+        credentials: LoginCredentials,
+        client_config: ClientConfig,
+        endpoints: Endpoints,
     ) -> eyre::Result<Self> {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(reqwest::header::USER_AGENT, get_safari_rua().parse()?);
-        headers.insert(reqwest::header::CONTENT_TYPE, "application/json".parse()?);
-        headers.insert("authorization", TOKEN.parse()?);
-        headers.insert("x-twitter-active-user", "yes".parse()?);
-        headers.insert("x-twitter-client-language", "en".parse()?);
+        let totp = credentials
+            .totp()
+            .map(|config| Arc::new(TotpGenerator::new(config.clone())));
+        let mut headers = default_headers()?;
+        let mut client_config = client_config;
+        client_config.pin_profile(&mut headers)?;
         let cookie_store = CookieStoreMutex::default();
         let cookie_store = Arc::new(cookie_store);
-        let mut client_builder = Client::builder().cookie_provider(cookie_store.clone());
-        if let Some(proxy) = proxy {
-            client_builder = client_builder.proxy(Proxy::all(proxy)?);
-        }
-        let client = client_builder.build()?;
+        // The flow sends its own headers with every request.
+        let client = client_config.build(cookie_store.clone(), HeaderMap::new())?;
+        let flow = OnboardingFlow::new(client, headers, cookie_store.clone(), endpoints);
         Ok(Self {
-            client,
+            flow,
             cookie_store,
-            headers,
-            username,
-            password,
-            totp_code,
-            email,
+            credentials,
+            client_config,
+            totp,
+            backup_codes: None,
+            sms_code_provider: None,
+            email_code_provider: None,
+            captcha_solver: None,
         })
     }
 
-    async fn get_guest_token(&mut self) -> eyre::Result<()> {
-        let response = self
-            .client
-            .post("https://api.x.com/1.1/guest/activate.json")
-            .headers(self.headers.clone())
-            .send()
-            .await?
-            .json::<serde_json::Value>()
-            .await?;
-        let guest_token = response["guest_token"].as_str().unwrap();
-        self.headers.insert("x-guest-token", guest_token.parse()?);
-        Ok(())
-    }
-
-    async fn init_login(&self) -> eyre::Result<TaskResponse> {
-        let payload = serde_json::json!({
-            "input_flow_data": {
-                "flow_context": {
-                    "debug_overrides": {},
-                    "start_location": {
-                        "location": "unknown"
-                    }
-                },
-                "subtask_versions": {}
-            }
-        });
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .query(&[("flow_name", "login")])
-            .json(&payload)
-            .send()
-            .await?;
-
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
-    }
-
-    async fn instrumentation(&self, flow_token: &str) -> eyre::Result<TaskResponse> {
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [
-                {
-                    "subtask_id": "LoginJsInstrumentationSubtask",
-                    "js_instrumentation": {"response": "{}", "link": "next_link"},
-                }
-            ],
-        });
+    /// Generates two-factor codes from `config` instead of the one in the
+    /// credentials.
+    pub fn with_totp(mut self, config: TotpConfig) -> Self {
+        self.totp = Some(Arc::new(TotpGenerator::new(config)));
+        self
+    }
 
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
-
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
-    }
-
-    async fn enter_username(&self, flow_token: &str) -> eyre::Result<TaskResponse> {
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [
-                {
-                    "subtask_id": "LoginEnterUserIdentifierSSO",
-                    "settings_list": {
-                        "setting_responses": [
-                            {
-                                "key": "user_identifier",
-                                "response_data": {"text_data": {"result": self.username}},
-                            }
-                        ],
-                        "link": "next_link",
-                    },
-                }
-            ],
-        });
+    /// One-time backup codes to answer the backup-code challenge with, used
+    /// when no TOTP secret or SMS provider can answer X's challenge.
+    pub fn with_backup_codes<I, S>(mut self, codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.backup_codes = Some(Arc::new(BackupCodes::new(codes)));
+        self
+    }
 
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
-
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
-    }
-
-    async fn enter_password(&self, flow_token: &str) -> eyre::Result<TaskResponse> {
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [
-                {
-                    "subtask_id": "LoginEnterPassword",
-                    "enter_password": {
-                        "password": self.password,
-                        "link": "next_link"
-                    },
-                }
-            ],
-        });
+    pub fn with_sms_code_provider(mut self, provider: impl SmsCodeProvider + 'static) -> Self {
+        self.sms_code_provider = Some(Arc::new(provider));
+        self
+    }
 
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
+    /// Backup codes submitted to X so far; they cannot be used again.
+    pub fn used_backup_codes(&self) -> Vec<String> {
+        self.backup_codes
+            .as_ref()
+            .map(|codes| codes.used())
+            .unwrap_or_default()
+    }
 
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
+    pub fn with_email_code_provider(mut self, provider: impl EmailCodeProvider + 'static) -> Self {
+        self.email_code_provider = Some(Arc::new(provider));
+        self
     }
 
-    async fn login_two_factor_auth_challenge(
-        &self,
-        flow_token: &str,
-    ) -> eyre::Result<TaskResponse> {
-        if self.totp_code.is_none() {
-            eyre::bail!("MFA code is required");
+    pub fn with_captcha_solver(mut self, solver: impl CaptchaSolver + 'static) -> Self {
+        self.captcha_solver = Some(Arc::new(solver));
+        self
+    }
+
+    /// Handles `subtask_id` with `handler`, taking precedence over the
+    /// built-in login handlers.
+    pub fn with_subtask_handler(
+        mut self,
+        subtask_id: &str,
+        handler: impl SubtaskHandler + 'static,
+    ) -> Self {
+        self.flow.register(subtask_id, Arc::new(handler));
+        self
+    }
+
+    fn login_subtasks(&self) -> LoginSubtasks {
+        LoginSubtasks {
+            credentials: self.credentials.clone(),
+            totp: self.totp.clone(),
+            backup_codes: self.backup_codes.clone(),
+            sms_code_provider: self.sms_code_provider.clone(),
+            email_code_provider: self.email_code_provider.clone(),
+            captcha_solver: self.captcha_solver.clone(),
         }
+    }
 
-        let totp = TOTP::new_unchecked(
-            Algorithm::SHA1,
-            6,
-            1,
-            30,
-            Secret::Encoded(self.totp_code.clone().unwrap())
-                .to_bytes()
-                .unwrap(),
-        );
-
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [
-                {
-                    "subtask_id": "LoginTwoFactorAuthChallenge",
-                    "enter_text": {
-                        "text": totp.generate_current().unwrap(),
-                        "link": "next_link"
-                    },
+    fn register_login_handlers(&mut self) {
+        let handler = Arc::new(self.login_subtasks());
+        for subtask_id in LOGIN_SUBTASKS.iter().chain(ARKOSE_SUBTASKS.iter()) {
+            self.flow.register_default(subtask_id, handler.clone());
+        }
+    }
+
+    async fn begin(&mut self) -> Result<FlowStatus, LoginError> {
+        self.register_login_handlers();
+        self.flow.activate_guest().await?;
+        let input_flow_data = serde_json::json!({
+            "flow_context": {
+                "debug_overrides": {},
+                "start_location": {
+                    "location": "unknown"
                 }
-            ],
+            },
+            "subtask_versions": {}
         });
+        let res = self.flow.start("login", input_flow_data).await?;
+        self.flow.run(res).await
+    }
 
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
+    async fn finish(&mut self) -> Result<AccountAuth, LoginError> {
+        self.flow.context_mut().sync_csrf_token()?;
+        let cookies = self.cookie_store.lock().unwrap().to_owned();
+        let account_auth = AccountAuth::new(self.flow.context().headers.clone(), cookies)
+            .with_client_config(self.client_config.clone());
+        self.confirm_user(account_auth).await
+    }
 
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
+    /// Asks X whose session the login produced, so a flow that quietly
+    /// switched accounts is caught before the session is saved. Returns the
+    /// session as it stands after asking, since X may rotate cookies on it.
+    async fn confirm_user(&self, account_auth: AccountAuth) -> Result<AccountAuth, LoginError> {
+        let account = Account::from_auth(account_auth, self.flow.endpoints().clone())?;
+        let user =
+            account
+                .verify_credentials()
+                .await
+                .map_err(|e| match e.downcast::<XApiError>() {
+                    Ok(error) => LoginError::from(error),
+                    Err(e) => LoginError::Other(e),
+                })?;
+        let mut account_auth = account.auth();
+        let expected = match (
+            &account_auth.info.user_id,
+            self.credentials.get(IdentifierKind::Username),
+        ) {
+            (Some(user_id), _) if *user_id != user.user_id => Some(user_id.clone()),
+            (_, Some(username)) if !username.eq_ignore_ascii_case(&user.screen_name) => {
+                Some(username.to_string())
+            }
+            _ => None,
+        };
+        if let Some(expected) = expected {
+            return Err(LoginError::WrongUser {
+                expected,
+                actual: user.screen_name,
+            });
+        }
+        account_auth.info.user_id = Some(user.user_id);
+        account_auth.info.screen_name = Some(user.screen_name);
+        Ok(account_auth)
     }
 
-    async fn login_success(&mut self, flow_token: &str) -> eyre::Result<()> {
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [],
-        });
+    /// Runs the whole login in one go. Subtasks that need input nobody was
+    /// configured to supply fail with the matching [`LoginError`].
+    pub async fn login(&mut self) -> Result<AccountAuth, LoginError> {
+        match self.begin().await? {
+            FlowStatus::Complete(_) => self.finish().await,
+            FlowStatus::Paused(subtask) => Err(match subtask.subtask_id.as_str() {
+                "LoginTwoFactorAuthChallenge" => subtask.two_factor_method().required(),
+                // None of the offered methods is configured; X lists the
+                // account's default first.
+                "LoginTwoFactorAuthChooseMethod" => {
+                    match subtask.offered_two_factor_methods().first() {
+                        Some(method) => method.required(),
+                        None => subtask.input_required(),
+                    }
+                }
+                "LoginAcid" if subtask.asks_for_code() => LoginError::EmailCodeRequired,
+                // X wants the account email retyped, and none was given.
+                "LoginAcid" | "LoginEnterAlternateIdentifierSubtask" => {
+                    LoginError::IdentifierRequired {
+                        prompt: subtask.prompt_text(),
+                    }
+                }
+                id if ARKOSE_SUBTASKS.contains(&id) => LoginError::CaptchaRequired,
+                _ => subtask.input_required(),
+            }),
+        }
+    }
+
+    /// Starts a login that stops at the first subtask needing outside input
+    /// instead of failing.
+    pub async fn start(mut self) -> Result<LoginStep, LoginError> {
+        let status = self.begin().await?;
+        self.step(status).await
+    }
+
+    async fn step(mut self, status: FlowStatus) -> Result<LoginStep, LoginError> {
+        match status {
+            FlowStatus::Complete(_) => {
+                let used_backup_codes = self.used_backup_codes();
+                Ok(LoginStep::Done {
+                    auth: self.finish().await?,
+                    used_backup_codes,
+                })
+            }
+            FlowStatus::Paused(subtask) => Ok(LoginStep::NeedsInput(Box::new(PendingLogin {
+                login: self,
+                subtask,
+            }))),
+        }
+    }
+
+    /// Picks up a login saved with [`PendingLogin::session`], possibly in
+    /// another process. Credentials and providers come from `self`; the flow,
+    /// headers and cookies come from the session.
+    pub fn resume(mut self, session: LoginSession) -> eyre::Result<PendingLogin> {
+        self.register_login_handlers();
+        // Finish as the browser that started the login.
+        if let Some(profile) = ClientProfile::from_headers(&session.headers) {
+            self.client_config = self.client_config.with_profile(profile);
+        }
+        let mut headers = header_map_from_strings(session.headers)?;
+        if let Some(guest_token) = &session.guest_token {
+            headers.insert("x-guest-token", guest_token.parse()?);
+        }
+        self.flow.context_mut().headers = headers;
+        #[allow(deprecated)]
+        let cookies =
+            CookieStore::load_json_all(session.cookies.as_bytes()).map_err(|e| eyre::eyre!(e))?;
+        *self.cookie_store.lock().unwrap() = cookies;
+        self.flow.set_flow_token(session.flow_token);
+        let subtask = serde_json::from_value(session.subtask)?;
+        Ok(PendingLogin {
+            login: self,
+            subtask,
+        })
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum LoginStep {
+    /// `used_backup_codes` lists the backup codes spent along the way.
+    Done {
+        auth: AccountAuth,
+        used_backup_codes: Vec<String>,
+    },
+    NeedsInput(Box<PendingLogin>),
+}
+
+/// What a paused login is waiting for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginPrompt {
+    EmailCode {
+        email: String,
+    },
+    /// The account's email, phone or username, whichever `prompt` names.
+    Identifier {
+        prompt: String,
+    },
+    TotpCode,
+    SmsCode {
+        prompt: String,
+    },
+    BackupCode,
+    Captcha(CaptchaChallenge),
+    Other {
+        subtask_id: String,
+    },
+}
 
-        let cookie_store = self.cookie_store.lock().unwrap();
-        let mut cookies = HashMap::new();
-        for cookie in cookie_store.iter_unexpired() {
-            cookies.insert(cookie.name().to_string(), cookie.value().to_string());
+/// A login waiting on input, e.g. an email code an operator has to paste.
+pub struct PendingLogin {
+    login: Login,
+    subtask: Subtask,
+}
+
+impl PendingLogin {
+    pub fn prompt(&self) -> LoginPrompt {
+        match self.subtask.subtask_id.as_str() {
+            "LoginAcid" if self.subtask.asks_for_code() => LoginPrompt::EmailCode {
+                email: self
+                    .login
+                    .credentials
+                    .get(IdentifierKind::Email)
+                    .unwrap_or_default()
+                    .to_string(),
+            },
+            "LoginAcid" | "LoginEnterAlternateIdentifierSubtask" => LoginPrompt::Identifier {
+                prompt: self.subtask.prompt_text(),
+            },
+            "LoginTwoFactorAuthChallenge" => match self.subtask.two_factor_method() {
+                TwoFactorMethod::Totp => LoginPrompt::TotpCode,
+                TwoFactorMethod::Sms => LoginPrompt::SmsCode {
+                    prompt: self.subtask.two_factor_prompt().to_string(),
+                },
+                TwoFactorMethod::BackupCode => LoginPrompt::BackupCode,
+            },
+            id => match CaptchaChallenge::from_subtask(&self.subtask) {
+                Some(challenge) if ARKOSE_SUBTASKS.contains(&id) => LoginPrompt::Captcha(challenge),
+                _ => LoginPrompt::Other {
+                    subtask_id: id.to_string(),
+                },
+            },
         }
-        let ct0_cookie = cookies.get("ct0").ok_or_eyre("ct0 cookie not found")?;
-        self.headers
-            .insert("x-csrf-token", ct0_cookie.parse().unwrap());
-        self.headers
-            .insert("x-twitter-auth-type", "OAuth2Session".parse().unwrap());
-        drop(cookie_store);
-
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
-        response.error_for_status()?;
-
-        let cookie_store = self.cookie_store.lock().unwrap();
-        let mut cookies = HashMap::new();
-        for cookie in cookie_store.iter_unexpired() {
-            cookies.insert(cookie.name().to_string(), cookie.value().to_string());
+    }
+
+    pub fn subtask(&self) -> &Subtask {
+        &self.subtask
+    }
+
+    /// Backup codes submitted to X so far; they cannot be used again.
+    pub fn used_backup_codes(&self) -> Vec<String> {
+        self.login.used_backup_codes()
+    }
+
+    /// Answers the pending subtask and continues until the login completes
+    /// or needs input again.
+    pub async fn submit(mut self, input: &str) -> Result<LoginStep, LoginError> {
+        let answer = self.login.login_subtasks().answer(&self.subtask, input)?;
+        let res = self.login.flow.submit(vec![answer]).await?;
+        let status = self.login.flow.run(res).await?;
+        self.login.step(status).await
+    }
+
+    /// Snapshot of the flow that can be stored and handed to
+    /// [`Login::resume`] later.
+    pub fn session(&self) -> LoginSession {
+        let context = self.login.flow.context();
+        let cookies = {
+            let cookie_store = self.login.cookie_store.lock().unwrap();
+            let mut buffer = Vec::new();
+            #[allow(deprecated)]
+            cookie_store
+                .save_incl_expired_and_nonpersistent_json(&mut buffer)
+                .unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+        LoginSession {
+            flow_token: self.login.flow.flow_token().unwrap_or_default().to_string(),
+            guest_token: context
+                .headers
+                .get("x-guest-token")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            headers: header_map_to_strings(&context.headers),
+            cookies,
+            subtask: self.subtask.raw.clone(),
         }
-        let ct0_cookie = cookies.get("ct0").ok_or_eyre("ct0 cookie not found")?;
-        self.headers
-            .insert("x-csrf-token", ct0_cookie.parse().unwrap());
-        drop(cookie_store);
-        Ok(())
     }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoginSession {
+    flow_token: String,
+    guest_token: Option<String>,
+    headers: HashMap<String, String>,
+    cookies: String,
+    subtask: serde_json::Value,
+}
 
-    async fn alternate_identifier(
+/// The built-in answers for X's login subtasks.
+struct LoginSubtasks {
+    credentials: LoginCredentials,
+    totp: Option<Arc<TotpGenerator>>,
+    backup_codes: Option<Arc<BackupCodes>>,
+    sms_code_provider: Option<Arc<dyn SmsCodeProvider>>,
+    email_code_provider: Option<Arc<dyn EmailCodeProvider>>,
+    captcha_solver: Option<Arc<dyn CaptchaSolver>>,
+}
+
+#[async_trait]
+impl SubtaskHandler for LoginSubtasks {
+    async fn handle(
         &self,
-        flow_token: &str,
-        email: &str,
-    ) -> eyre::Result<TaskResponse> {
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [
-                {
-                    "subtask_id": "LoginEnterAlternateIdentifierSubtask",
-                    "enter_text": {
-                        "text": email,
-                        "link": "next_link"
-                    }
-                }
-            ]
-        });
+        subtask: &Subtask,
+        context: &mut FlowContext,
+    ) -> Result<SubtaskOutcome, LoginError> {
+        let input = match subtask.subtask_id.as_str() {
+            "LoginJsInstrumentationSubtask" => Some(self.instrumentation()),
+            "LoginEnterUserIdentifierSSO" => Some(self.enter_username()),
+            "LoginEnterPassword" => Some(self.enter_password()),
+            "LoginTwoFactorAuthChallenge" => {
+                self.login_two_factor_auth_challenge(subtask, context)
+                    .await?
+            }
+            "LoginTwoFactorAuthChooseMethod" => self.choose_two_factor_method(subtask),
+            "LoginEnterAlternateIdentifierSubtask" => self
+                .credentials
+                .alternate_identifier(&subtask.prompt_text())
+                .map(|identifier| self.alternate_identifier(identifier)),
+            "LoginAcid" if subtask.asks_for_code() => self
+                .email_code()
+                .await?
+                .map(|code| self.confirm_email(&code)),
+            "LoginAcid" => self
+                .credentials
+                .get(IdentifierKind::Email)
+                .map(|email| self.confirm_email(email)),
+            id if ARKOSE_SUBTASKS.contains(&id) => self.arkose_challenge(subtask).await?,
+            "LoginSuccessSubtask" => return self.login_success(context),
+            "DenyLoginSubtask" => {
+                return Err(LoginError::Denied {
+                    message: subtask.deny_message(),
+                })
+            }
+            _ => {
+                return Err(LoginError::UnknownSubtask {
+                    id: subtask.subtask_id.clone(),
+                    raw: subtask.raw.clone(),
+                })
+            }
+        };
+        // Without a configured source for the input, hand the subtask back
+        // to the caller rather than failing.
+        Ok(match input {
+            Some(input) => SubtaskOutcome::Submit(vec![input]),
+            None => SubtaskOutcome::Pause,
+        })
+    }
+}
 
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
-
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
-    }
-
-    async fn confirm_email(&self, flow_token: &str) -> eyre::Result<TaskResponse> {
-        let payload = serde_json::json!({
-            "flow_token": flow_token,
-            "subtask_inputs": [
-                {
-                    "subtask_id": "LoginAcid",
-                    "enter_text": {
-                        "text": self.email,
-                        "link": "next_link"
+impl LoginSubtasks {
+    fn instrumentation(&self) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginJsInstrumentationSubtask",
+            "js_instrumentation": {"response": "{}", "link": "next_link"},
+        })
+    }
+
+    fn enter_username(&self) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginEnterUserIdentifierSSO",
+            "settings_list": {
+                "setting_responses": [
+                    {
+                        "key": "user_identifier",
+                        "response_data": {"text_data": {"result": self.credentials.identifier()}},
                     }
-                }
-            ]
-        });
+                ],
+                "link": "next_link",
+            },
+        })
+    }
+
+    fn enter_password(&self) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginEnterPassword",
+            "enter_password": {
+                "password": self.credentials.password().expose(),
+                "link": "next_link"
+            },
+        })
+    }
+
+    /// Second factors that can be answered without asking the caller, in
+    /// the order they are preferred.
+    fn two_factor_methods(&self) -> Vec<TwoFactorMethod> {
+        let mut methods = Vec::new();
+        if self.totp.is_some() {
+            methods.push(TwoFactorMethod::Totp);
+        }
+        if self.sms_code_provider.is_some() {
+            methods.push(TwoFactorMethod::Sms);
+        }
+        if self
+            .backup_codes
+            .as_ref()
+            .is_some_and(|codes| !codes.is_empty())
+        {
+            methods.push(TwoFactorMethod::BackupCode);
+        }
+        methods
+    }
 
-        let response = self
-            .client
-            .post(LOGIN_URL)
-            .headers(self.headers.clone())
-            .json(&payload)
-            .send()
-            .await?;
-
-        let task_response: TaskResponse = response.json().await?;
-        Ok(task_response)
-    }
-
-    pub async fn login(&mut self) -> eyre::Result<AccountAuth> {
-        self.get_guest_token().await?;
-        let res = self.init_login().await?;
-        let mut res = self.instrumentation(&res.flow_token).await?;
-        loop {
-            if res.subtasks.len() == 0 {
-                break;
+    async fn login_two_factor_auth_challenge(
+        &self,
+        subtask: &Subtask,
+        context: &FlowContext,
+    ) -> Result<Option<serde_json::Value>, LoginError> {
+        let code = match subtask.two_factor_method() {
+            TwoFactorMethod::Totp => match &self.totp {
+                Some(totp) => Some(totp.code(context.clock_offset()).await),
+                None => None,
+            },
+            TwoFactorMethod::Sms => match &self.sms_code_provider {
+                Some(provider) => Some(provider.sms_code(subtask.two_factor_prompt()).await?),
+                None => None,
+            },
+            TwoFactorMethod::BackupCode => {
+                self.backup_codes.as_ref().and_then(|codes| codes.take())
             }
-            res = match res.subtasks[0].subtask_id.as_str() {
-                "LoginEnterUserIdentifierSSO" => self.enter_username(&res.flow_token).await?,
-                "LoginEnterPassword" => self.enter_password(&res.flow_token).await?,
-                "LoginTwoFactorAuthChallenge" => {
-                    self.login_two_factor_auth_challenge(&res.flow_token)
-                        .await?
-                }
-                "LoginSuccessSubtask" => {
-                    self.login_success(&res.flow_token).await?;
-                    break;
-                }
-                "LoginEnterAlternateIdentifierSubtask" => {
-                    self.alternate_identifier(&res.flow_token, &self.email)
-                        .await?
-                }
-                "LoginAcid" => {
-                    // todo: handle case where email code is required
-                    self.confirm_email(&res.flow_token).await?
-                }
-                _ => {
-                    eyre::bail!("Login Failed: {:?}", res);
-                }
-            };
+        };
+        if let Some(code) = code {
+            return Ok(Some(self.two_factor_code(&code)));
+        }
+        // X picked a method we cannot answer; ask for another if one is
+        // configured.
+        match subtask.choose_method_link() {
+            Some(link) if !self.two_factor_methods().is_empty() => Ok(Some(serde_json::json!({
+                "subtask_id": "LoginTwoFactorAuthChallenge",
+                "enter_text": {"link": link},
+            }))),
+            _ => Ok(None),
         }
-        let cookie_store = self.cookie_store.lock().unwrap();
-        let cookies = cookie_store.to_owned();
-        drop(cookie_store);
-        let account_auth = AccountAuth::new(self.headers.clone(), cookies);
-        Ok(account_auth)
     }
+
+    fn choose_two_factor_method(&self, subtask: &Subtask) -> Option<serde_json::Value> {
+        let choices = subtask.raw["choice_selection"]["choices"].as_array()?;
+        let choice = self.two_factor_methods().into_iter().find_map(|method| {
+            choices.iter().find(|choice| {
+                choice["text"]["text"]
+                    .as_str()
+                    .is_some_and(|text| TwoFactorMethod::from_text(text) == method)
+            })
+        })?;
+        Some(self.two_factor_choice(choice["id"].as_str()?))
+    }
+
+    fn two_factor_choice(&self, choice_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginTwoFactorAuthChooseMethod",
+            "choice_selection": {
+                "link": "next_link",
+                "selected_choices": [choice_id]
+            },
+        })
+    }
+
+    fn two_factor_code(&self, code: &str) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginTwoFactorAuthChallenge",
+            "enter_text": {
+                "text": code,
+                "link": "next_link"
+            },
+        })
+    }
+
+    fn login_success(&self, context: &mut FlowContext) -> Result<SubtaskOutcome, LoginError> {
+        context.sync_csrf_token()?;
+        context
+            .headers
+            .insert("x-twitter-auth-type", "OAuth2Session".parse()?);
+        Ok(SubtaskOutcome::Finish(vec![]))
+    }
+
+    fn alternate_identifier(&self, identifier: &str) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginEnterAlternateIdentifierSubtask",
+            "enter_text": {
+                "text": identifier,
+                "link": "next_link"
+            }
+        })
+    }
+
+    fn confirm_email(&self, text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "subtask_id": "LoginAcid",
+            "enter_text": {
+                "text": text,
+                "link": "next_link"
+            }
+        })
+    }
+
+    async fn arkose_challenge(
+        &self,
+        subtask: &Subtask,
+    ) -> Result<Option<serde_json::Value>, LoginError> {
+        let challenge = arkose_challenge(subtask)?;
+        let Some(solver) = &self.captcha_solver else {
+            return Ok(None);
+        };
+        let token = solver.solve(&challenge).await?;
+        Ok(Some(challenge.response(&token)))
+    }
+
+    async fn email_code(&self) -> Result<Option<String>, LoginError> {
+        match &self.email_code_provider {
+            Some(provider) => {
+                let email = self.credentials.get(IdentifierKind::Email);
+                Ok(Some(provider.email_code(email.unwrap_or_default()).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Builds the answer to a paused subtask from input supplied by the
+    /// caller.
+    fn answer(&self, subtask: &Subtask, input: &str) -> Result<serde_json::Value, LoginError> {
+        Ok(match subtask.subtask_id.as_str() {
+            "LoginAcid" => self.confirm_email(input),
+            "LoginTwoFactorAuthChallenge" => self.two_factor_code(input),
+            "LoginTwoFactorAuthChooseMethod" => self.two_factor_choice(input),
+            id if ARKOSE_SUBTASKS.contains(&id) => arkose_challenge(subtask)?.response(input),
+            id => serde_json::json!({
+                "subtask_id": id,
+                "enter_text": {
+                    "text": input,
+                    "link": "next_link"
+                }
+            }),
+        })
+    }
+}
+
+fn arkose_challenge(subtask: &Subtask) -> Result<CaptchaChallenge, LoginError> {
+    Ok(CaptchaChallenge::from_subtask(subtask)
+        .ok_or_else(|| eyre::eyre!("no Arkose public key in {}", subtask.subtask_id))?)
 }
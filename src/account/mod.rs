@@ -1,87 +1,545 @@
 use std::{
     collections::HashMap,
-    path::{Path, PathBuf},
-    sync::Arc,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use reqwest::{header::HeaderMap, Client};
+use reqwest::{header::HeaderMap, Client, Url};
 use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
 use serde::{Deserialize, Serialize};
 
+pub use client::{ClientConfig, ClientProfile};
+pub use credentials::LoginCredentials;
+pub use encryption::AuthKey;
+pub use endpoints::Endpoints;
+pub use error::{LoginError, XApiError, XErrorCode};
+pub use reauth::{CredentialsProvider, ReauthPolicy};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteAuthStore;
+pub use store::{AuthStore, FileAuthStore, MemoryAuthStore, SaveOutcome};
+pub use user::UserProfile;
+
+const EMAIL_PHONE_INFO_PATH: &str = "/i/api/1.1/users/email_phone_info.json";
+// X's own session cookies last about five years.
+const SESSION_COOKIE_MAX_AGE: u64 = 5 * 365 * 24 * 60 * 60;
+/// The [`AccountAuth`] format written by this version of the crate. Files
+/// without a `version` field are the original headers-and-cookies format.
+pub const AUTH_VERSION: u32 = 3;
+/// Headers that only mean something for the request they were sent with.
+const TRANSIENT_HEADERS: [&str; 3] = ["x-guest-token", "content-type", "content-length"];
+
+pub mod captcha;
+pub mod client;
+mod cookies;
+pub mod credentials;
+pub mod email;
+pub mod encryption;
+pub mod endpoints;
+pub mod error;
+pub mod flow;
 pub mod login;
 pub mod oauth;
 pub mod password;
+pub mod phone;
+pub mod reauth;
+mod request;
+mod response;
+pub mod store;
+pub mod totp;
+pub mod user;
+pub mod verification;
 
 pub struct Account {
     client: Client,
     cookie_store: Arc<CookieStoreMutex>,
     headers: HeaderMap,
-    auth_path: Option<PathBuf>,
+    endpoints: Endpoints,
+    info: Mutex<AuthInfo>,
+    client_config: ClientConfig,
+    saved: Mutex<request::Saved>,
+    persistence: Option<Persistence>,
+    reauth: Option<ReauthPolicy>,
+    /// Held while logging in again, so concurrent failures log in once.
+    reauth_lock: tokio::sync::Mutex<()>,
+    /// Cached by [`Account::verify_credentials`].
+    user: Mutex<Option<UserProfile>>,
+}
+
+/// Where [`Account::save`] writes the session.
+enum Persistence {
+    File(store::AuthFile),
+    Store {
+        store: Arc<dyn AuthStore>,
+        account_id: String,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// What a saved session records about itself besides headers and cookies.
+/// Times are seconds since the epoch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuthInfo {
+    /// Numeric user id, from the `twid` cookie.
+    pub user_id: Option<String>,
+    pub screen_name: Option<String>,
+    pub created_at: Option<u64>,
+    pub last_refreshed_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredAuth")]
 pub struct AccountAuth {
+    version: u32,
+    /// Bumped on every save, so writers can tell whether someone else saved
+    /// since they loaded.
+    revision: u64,
+    #[serde(flatten)]
+    info: AuthInfo,
+    client: ClientConfig,
     headers: HashMap<String, String>,
     cookies: String,
 }
 
+/// Any `AccountAuth` file this crate has written, before migration.
+#[derive(Deserialize)]
+struct StoredAuth {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    revision: u64,
+    #[serde(flatten)]
+    info: AuthInfo,
+    #[serde(default)]
+    client: ClientConfig,
+    /// v1 kept the proxy next to the rest of [`AuthInfo`].
+    #[serde(default)]
+    proxy: Option<String>,
+    headers: HashMap<String, String>,
+    cookies: String,
+}
+
+impl TryFrom<StoredAuth> for AccountAuth {
+    type Error = String;
+
+    fn try_from(stored: StoredAuth) -> Result<Self, Self::Error> {
+        let mut auth = Self {
+            version: AUTH_VERSION,
+            revision: stored.revision,
+            info: stored.info,
+            client: stored.client,
+            headers: stored.headers,
+            cookies: stored.cookies,
+        };
+        if stored.version > AUTH_VERSION {
+            return Err(format!(
+                "unsupported AccountAuth version {}",
+                stored.version
+            ));
+        }
+        if stored.version == 0 {
+            // v0 files carry nothing but headers and cookies; recover what
+            // can be read from them.
+            let cookies = auth.cookie_store().map_err(|e| e.to_string())?;
+            auth.info.user_id = user_id_from_cookies(&cookies);
+        }
+        if let Some(proxy) = stored.proxy.filter(|_| stored.version == 1) {
+            auth.client = auth.client.with_proxy(proxy);
+        }
+        if stored.version < 3 {
+            // The browser the session claimed to be was only in its headers.
+            if let Some(profile) = ClientProfile::from_headers(&auth.headers) {
+                auth.client = auth.client.with_profile(profile);
+            }
+            scrub_transient_headers(&mut auth.headers);
+        }
+        Ok(auth)
+    }
+}
+
+fn scrub_transient_headers(headers: &mut HashMap<String, String>) {
+    headers.retain(|name, _| !TRANSIENT_HEADERS.contains(&name.as_str()));
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// X keeps the logged-in user's id in the `twid` cookie as `u=<id>`,
+/// URL-encoded and sometimes quoted.
+fn user_id_from_cookies(cookies: &CookieStore) -> Option<String> {
+    let twid = cookies.iter_any().find(|cookie| cookie.name() == "twid")?;
+    let value = twid
+        .value()
+        .trim_matches('"')
+        .replace("%3D", "=")
+        .replace("%3d", "=");
+    value
+        .strip_prefix("u=")
+        .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+pub(crate) fn header_map_to_strings(headers: &HeaderMap) -> HashMap<String, String> {
+    headers
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect()
+}
+
+pub(crate) fn header_map_from_strings(headers: HashMap<String, String>) -> eyre::Result<HeaderMap> {
+    let mut header_map = HeaderMap::new();
+    for (k, v) in headers {
+        header_map.insert(
+            reqwest::header::HeaderName::from_bytes(k.as_bytes())?,
+            reqwest::header::HeaderValue::from_str(&v)?,
+        );
+    }
+    Ok(header_map)
+}
+
+/// Stores `name=value` as X would set it for `url`: for the whole parent
+/// domain, e.g. `.x.com`, unless the host is an IP address.
+fn insert_session_cookie(
+    cookie_store: &mut CookieStore,
+    name: &str,
+    value: &str,
+    url: &Url,
+) -> eyre::Result<()> {
+    let mut cookie = format!("{name}={value}; Path=/; Max-Age={SESSION_COOKIE_MAX_AGE}");
+    if let Some(host) = url.domain() {
+        let domain = host.strip_prefix("api.").unwrap_or(host);
+        cookie.push_str(&format!("; Domain=.{domain}"));
+    }
+    if url.scheme() == "https" {
+        cookie.push_str("; Secure");
+    }
+    cookie_store.parse(&cookie, url)?;
+    Ok(())
+}
+
 impl AccountAuth {
     pub fn new(headers: HeaderMap, cookies: CookieStore) -> Self {
-        let headers_map: HashMap<String, String> = headers
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
+        let mut headers_map = header_map_to_strings(&headers);
+        scrub_transient_headers(&mut headers_map);
         let cookies_string = {
             let mut buffer = Vec::new();
             {
                 let mut writer = std::io::BufWriter::new(&mut buffer);
+                #[allow(deprecated)]
                 cookies.save_json(&mut writer).unwrap();
             }
             String::from_utf8(buffer).unwrap()
         };
+        let info = AuthInfo {
+            user_id: user_id_from_cookies(&cookies),
+            created_at: Some(unix_now()),
+            ..Default::default()
+        };
+        let mut client = ClientConfig::default();
+        if let Some(profile) = ClientProfile::from_headers(&headers_map) {
+            client = client.with_profile(profile);
+        }
         Self {
+            version: AUTH_VERSION,
+            revision: 0,
+            info,
+            client,
             headers: headers_map,
             cookies: cookies_string,
         }
     }
+
+    pub fn with_screen_name(mut self, screen_name: impl Into<String>) -> Self {
+        self.info.screen_name = Some(screen_name.into());
+        self
+    }
+
+    pub fn with_client_config(mut self, client: ClientConfig) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub(crate) fn with_revision(mut self, revision: u64) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn info(&self) -> &AuthInfo {
+        &self.info
+    }
+
+    pub fn client(&self) -> &ClientConfig {
+        &self.client
+    }
+
+    pub(crate) fn cookie_store(&self) -> eyre::Result<CookieStore> {
+        #[allow(deprecated)]
+        CookieStore::load_json(self.cookies.as_bytes()).map_err(|e| eyre::eyre!(e))
+    }
 }
 
 impl Account {
-    pub fn from_auth(auth: AccountAuth) -> eyre::Result<Self> {
-        let header_map = HeaderMap::from_iter(auth.headers.into_iter().map(|(k, v)| {
-            (
-                reqwest::header::HeaderName::from_bytes(k.as_bytes()).unwrap(),
-                reqwest::header::HeaderValue::from_str(&v).unwrap(),
-            )
-        }));
-        let cookie_store =
-            CookieStore::load_json(auth.cookies.as_bytes()).map_err(|e| eyre::eyre!(e))?;
+    pub fn from_auth(auth: AccountAuth, endpoints: Endpoints) -> eyre::Result<Self> {
+        let cookie_store = auth.cookie_store()?;
+        let mut header_map = header_map_from_strings(auth.headers)?;
+        // Sent per request from the live `ct0` instead.
+        header_map.remove("x-csrf-token");
+        let mut client_config = auth.client;
+        client_config.pin_profile(&mut header_map)?;
+        let saved = request::Saved::new(auth.revision, &cookie_store);
         let cookie_store = Arc::new(reqwest_cookie_store::CookieStoreMutex::new(cookie_store));
-        let client = Client::builder()
-            .cookie_provider(cookie_store.clone())
-            .default_headers(header_map.clone())
-            .build()
-            .unwrap();
+        let client = client_config.build(cookie_store.clone(), header_map.clone())?;
         Ok(Self {
             client,
             cookie_store,
             headers: header_map,
-            auth_path: None,
+            endpoints,
+            info: Mutex::new(auth.info),
+            client_config,
+            saved: Mutex::new(saved),
+            persistence: None,
+            reauth: None,
+            reauth_lock: Default::default(),
+            user: Mutex::new(None),
         })
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> eyre::Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        let auth: AccountAuth = serde_json::from_reader(std::fs::File::open(path.clone())?)?;
-        let mut account = Self::from_auth(auth)?;
-        account.auth_path = Some(path);
+    pub fn from_file<P: AsRef<Path>>(path: P, endpoints: Endpoints) -> eyre::Result<Self> {
+        Self::from_auth_file(
+            store::AuthFile {
+                path: path.as_ref().to_path_buf(),
+                key: None,
+            },
+            endpoints,
+        )
+    }
+
+    /// Loads a session saved with [`AccountAuth::encrypt`]. The file stays
+    /// encrypted with the same key when cookies are refreshed.
+    pub fn from_encrypted_file<P: AsRef<Path>>(
+        path: P,
+        key: AuthKey,
+        endpoints: Endpoints,
+    ) -> eyre::Result<Self> {
+        Self::from_auth_file(
+            store::AuthFile {
+                path: path.as_ref().to_path_buf(),
+                key: Some(key),
+            },
+            endpoints,
+        )
+    }
+
+    fn from_auth_file(file: store::AuthFile, endpoints: Endpoints) -> eyre::Result<Self> {
+        let auth = file
+            .load()?
+            .ok_or_else(|| eyre::eyre!("no auth file at {}", file.path.display()))?;
+        let mut account = Self::from_auth(auth, endpoints)?;
+        account.persistence = Some(Persistence::File(file));
+        Ok(account)
+    }
+
+    /// Loads the session saved under `account_id`. Refreshed cookies are
+    /// saved back to the same store.
+    pub async fn from_store(
+        store: Arc<dyn AuthStore>,
+        account_id: &str,
+        endpoints: Endpoints,
+    ) -> eyre::Result<Self> {
+        let auth = store
+            .load(account_id)
+            .await?
+            .ok_or_else(|| eyre::eyre!("no auth stored for {account_id:?}"))?;
+        Ok(Self::from_auth(auth, endpoints)?.with_store(store, account_id))
+    }
+
+    /// Saves the session to `store` under `account_id` from now on, e.g.
+    /// for an account built with [`Account::from_auth_token`].
+    pub fn with_store(mut self, store: Arc<dyn AuthStore>, account_id: &str) -> Self {
+        self.persistence = Some(Persistence::Store {
+            store,
+            account_id: account_id.to_string(),
+        });
+        self
+    }
+
+    /// Logs in again with `policy` whenever X stops accepting the session,
+    /// then retries the request that found out.
+    pub fn with_reauth(mut self, policy: ReauthPolicy) -> Self {
+        self.reauth = Some(policy);
+        self
+    }
+
+    /// Writes the current session wherever it was loaded from. If another
+    /// process saved since, its newer session is loaded instead of being
+    /// overwritten. Does nothing for accounts without a file or store.
+    pub async fn save(&mut self) -> eyre::Result<()> {
+        if let Some(newer) = self.compare_and_swap().await? {
+            self.replace_auth(newer)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the session unless someone else saved first, in which case
+    /// their session is returned.
+    async fn compare_and_swap(&self) -> eyre::Result<Option<AccountAuth>> {
+        let auth = self.auth();
+        let outcome = match &self.persistence {
+            Some(Persistence::File(file)) => file.compare_and_swap(&auth).await?,
+            Some(Persistence::Store { store, account_id }) => {
+                store.compare_and_swap(account_id, &auth).await?
+            }
+            None => return Ok(None),
+        };
+        match outcome {
+            SaveOutcome::Saved(revision) => {
+                *self.saved.lock().unwrap() = request::Saved::new(revision, &auth.cookie_store()?);
+                Ok(None)
+            }
+            SaveOutcome::Stale(newer) => Ok(Some(*newer)),
+        }
+    }
+
+    /// Picks up a session another process saved since this one was loaded.
+    /// Returns whether there was one.
+    pub async fn reload(&mut self) -> eyre::Result<bool> {
+        let saved = match &self.persistence {
+            Some(Persistence::File(file)) => file.load()?,
+            Some(Persistence::Store { store, account_id }) => store.load(account_id).await?,
+            None => None,
+        };
+        match saved {
+            Some(saved) if saved.revision > self.saved.lock().unwrap().revision => {
+                self.replace_auth(saved)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn replace_auth(&mut self, auth: AccountAuth) -> eyre::Result<()> {
+        self.replace_cookies(&auth)?;
+        self.headers = header_map_from_strings(auth.headers)?;
+        self.headers.remove("x-csrf-token");
+        *self.info.get_mut().unwrap() = auth.info;
+        self.client_config = auth.client;
+        self.client_config.pin_profile(&mut self.headers)?;
+        self.client = self
+            .client_config
+            .build(self.cookie_store.clone(), self.headers.clone())?;
+        Ok(())
+    }
+
+    /// Swaps in the cookies of `auth`, which is what was saved at its
+    /// revision.
+    fn replace_cookies(&self, auth: &AccountAuth) -> eyre::Result<()> {
+        let cookies = auth.cookie_store()?;
+        *self.saved.lock().unwrap() = request::Saved::new(auth.revision, &cookies);
+        *self.cookie_store.lock().unwrap() = cookies;
+        Ok(())
+    }
+
+    /// Builds a session from a bare `auth_token` cookie, e.g. one exported
+    /// from a browser. Without a `ct0`, X is asked to issue one. Fails if X
+    /// does not accept the session.
+    pub async fn from_auth_token(
+        auth_token: &str,
+        ct0: Option<&str>,
+        client_config: ClientConfig,
+        endpoints: Endpoints,
+    ) -> eyre::Result<Self> {
+        let url = Url::parse(&endpoints.web("/"))?;
+        let mut cookie_store = CookieStore::default();
+        insert_session_cookie(&mut cookie_store, "auth_token", auth_token, &url)?;
+        let mut headers = login::default_headers()?;
+        headers.insert("x-twitter-auth-type", "OAuth2Session".parse()?);
+        if let Some(ct0) = ct0 {
+            insert_session_cookie(&mut cookie_store, "ct0", ct0, &url)?;
+        }
+        let info = AuthInfo {
+            user_id: user_id_from_cookies(&cookie_store),
+            created_at: Some(unix_now()),
+            ..Default::default()
+        };
+        let mut client_config = client_config;
+        client_config.pin_profile(&mut headers)?;
+        let saved = request::Saved::new(0, &cookie_store);
+        let cookie_store = Arc::new(CookieStoreMutex::new(cookie_store));
+        let client = client_config.build(cookie_store.clone(), headers.clone())?;
+        let account = Self {
+            client,
+            cookie_store,
+            headers,
+            endpoints,
+            info: Mutex::new(info),
+            client_config,
+            saved: Mutex::new(saved),
+            persistence: None,
+            reauth: None,
+            reauth_lock: Default::default(),
+            user: Mutex::new(None),
+        };
+        if ct0.is_none() {
+            account.fetch_ct0().await?;
+        }
+        account.get_email_phone_info().await?;
         Ok(account)
     }
 
+    /// X answers a request without a CSRF token by rejecting it and setting
+    /// a fresh `ct0` cookie.
+    async fn fetch_ct0(&self) -> eyre::Result<()> {
+        let url = self.endpoints.web(EMAIL_PHONE_INFO_PATH);
+        let response = self.send(self.get(&url)?).await?;
+        if self.ct0().is_none() {
+            // A dead session gets no ct0; report X's error if it sent one.
+            response.ensure_success()?;
+            eyre::bail!("X did not issue a ct0 cookie");
+        }
+        Ok(())
+    }
+
+    pub fn info(&self) -> AuthInfo {
+        self.info.lock().unwrap().clone()
+    }
+
+    pub fn client_config(&self) -> &ClientConfig {
+        &self.client_config
+    }
+
+    /// The session as it stands, ready to be saved.
+    pub fn auth(&self) -> AccountAuth {
+        let cookies = self.cookie_store.lock().unwrap().to_owned();
+        let mut headers = self.headers.clone();
+        // Kept for readers of the saved headers; requests use the cookie.
+        if let Some(ct0) = self.ct0().and_then(|ct0| ct0.parse().ok()) {
+            headers.insert("x-csrf-token", ct0);
+        }
+        let mut auth = AccountAuth::new(headers, cookies);
+        auth.revision = self.saved.lock().unwrap().revision;
+        auth.client = self.client_config.clone();
+        let info = self.info();
+        auth.info = AuthInfo {
+            user_id: auth.info.user_id.or_else(|| info.user_id.clone()),
+            ..info
+        };
+        auth
+    }
+
     pub async fn get_email_phone_info(&self) -> eyre::Result<EmailPhoneResponse> {
-        let url = "https://x.com/i/api/1.1/users/email_phone_info.json";
-        let response: EmailPhoneResponse = self.client.get(url).send().await?.json().await?;
-        Ok(response)
+        let url = self.endpoints.web(EMAIL_PHONE_INFO_PATH);
+        Ok(self.send(self.get(&url)?).await?.json()?)
     }
 
     pub fn auth_cookie_string(&self) -> String {
@@ -96,17 +554,105 @@ impl Account {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct EmailInfo {
     pub email: String,
     pub email_verified: bool,
+    /// The address X sends login codes and notices to.
+    #[serde(default)]
+    pub primary: bool,
+    /// Added, but waiting for the confirmation code sent to it.
+    #[serde(default)]
+    pub pending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "RawPhoneInfo")]
+pub struct PhoneInfo {
+    pub id: Option<String>,
+    /// E.164 when X shows it in full; masked, e.g. `+1*******23`, otherwise.
+    pub phone_number: String,
+    #[serde(rename = "phone_number_verified")]
+    pub verified: bool,
+    pub carrier: Option<String>,
+    /// ISO 3166 code, e.g. `US`.
+    pub country_code: Option<String>,
+}
+
+impl PhoneInfo {
+    /// Whether X hid some digits, leaving the number usable only to tell
+    /// phones apart.
+    pub fn is_masked(&self) -> bool {
+        self.phone_number.contains('*')
+    }
+
+    /// Whether this could be `phone_number`, comparing only the digits X
+    /// left visible.
+    pub fn matches(&self, phone_number: &str) -> bool {
+        match (self.phone_number.find('*'), self.phone_number.rfind('*')) {
+            (Some(first), Some(last)) => {
+                let prefix = &self.phone_number[..first];
+                let suffix = &self.phone_number[last + 1..];
+                phone_number.len() >= prefix.len() + suffix.len()
+                    && phone_number.starts_with(prefix)
+                    && phone_number.ends_with(suffix)
+            }
+            _ => self.phone_number == phone_number,
+        }
+    }
+}
+
+/// A phone entry as X sends it, usually with the id twice: as a number,
+/// which may not survive a round trip through a double, and as `id_str`.
+#[derive(Deserialize)]
+struct RawPhoneInfo {
+    #[serde(default, deserialize_with = "string_or_number")]
+    id: Option<String>,
+    #[serde(default)]
+    id_str: Option<String>,
+    phone_number: String,
+    #[serde(default)]
+    phone_number_verified: bool,
+    #[serde(default)]
+    carrier: Option<String>,
+    #[serde(default)]
+    country_code: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct PhoneInfo();
+impl From<RawPhoneInfo> for PhoneInfo {
+    fn from(raw: RawPhoneInfo) -> Self {
+        Self {
+            id: raw.id_str.or(raw.id),
+            phone_number: raw.phone_number,
+            verified: raw.phone_number_verified,
+            carrier: raw.carrier,
+            country_code: raw.country_code,
+        }
+    }
+}
+
+/// X sends ids as strings in some responses and as numbers in others.
+fn string_or_number<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        String(String),
+        Number(u64),
+    }
+    Ok(Option::<Id>::deserialize(deserializer)?.map(|id| match id {
+        Id::String(id) => id,
+        Id::Number(id) => id.to_string(),
+    }))
+}
 
-#[derive(Debug, Deserialize)]
+/// Everything attached to an account that X could use to reach or recover
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct EmailPhoneResponse {
+    #[serde(default)]
     pub emails: Vec<EmailInfo>,
+    #[serde(default)]
     pub phone_numbers: Vec<PhoneInfo>,
 }
@@ -4,6 +4,9 @@ use serde::Deserialize;
 
 use super::Account;
 
+const OAUTH_REVOKE_PATH: &str = "/i/api/1.1/oauth/revoke.json";
+const OAUTH_LIST_PATH: &str = "/1.1/oauth/list.json";
+
 #[derive(Deserialize, Debug)]
 pub struct Application {
     pub token: String,
@@ -19,24 +22,17 @@ impl Account {
     pub async fn revoke_oauth_application(&self, token: &str) -> eyre::Result<()> {
         let mut params = HashMap::new();
         params.insert("token", token.to_string());
-        let response = self
-            .client
-            .post("https://x.com/i/api/1.1/oauth/revoke.json")
+        let request = self
+            .post(&self.endpoints.web(OAUTH_REVOKE_PATH))?
             .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&params)
-            .send()
-            .await?;
-        response.error_for_status()?;
+            .form(&params);
+        self.send(request).await?.ensure_success()?;
         Ok(())
     }
 
     pub async fn get_all_oauth_applications(&self) -> eyre::Result<Vec<Application>> {
-        let response = self
-            .client
-            .get("https://api.x.com/1.1/oauth/list.json")
-            .send()
-            .await?;
-        let response: OAuthApplicationList = response.json().await?;
+        let request = self.get(&self.endpoints.api(OAUTH_LIST_PATH))?;
+        let response: OAuthApplicationList = self.send(request).await?.json()?;
         Ok(response.applications.unwrap_or_default())
     }
 
@@ -1,10 +1,9 @@
 use std::collections::HashMap;
 
-use reqwest::Client;
+use super::{response, unix_now, Account};
 
-use super::{Account, AccountAuth};
-
-const CHANGE_PASSWORD_URL: &str = "https://x.com/i/api/i/account/change_password.json";
+const CHANGE_PASSWORD_PATH: &str = "/i/api/i/account/change_password.json";
+const NOTIFICATIONS_PATH: &str = "/i/api/2/notifications/all.json";
 
 impl Account {
     pub async fn change_password(&self, old: &str, new: &str) -> eyre::Result<()> {
@@ -13,43 +12,26 @@ impl Account {
         params.insert("password", new);
         params.insert("password_confirmation", new);
 
-        let response = self
-            .client
-            .post(CHANGE_PASSWORD_URL)
+        let request = self
+            .post(&self.endpoints.web(CHANGE_PASSWORD_PATH))?
             .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&params)
-            .send()
-            .await?;
-        if !response.status().is_success() {
-            let body = response.text().await?;
-            eyre::bail!("Password change failed: {}", body);
-        }
+            .form(&params);
+        self.send(request)
+            .await?
+            .ensure_success()
+            .map_err(|e| eyre::Report::new(e).wrap_err("Password change failed"))?;
         Ok(())
     }
 
     pub async fn refresh_cookies(&mut self) -> eyre::Result<()> {
-        let url = "https://x.com/i/api/2/notifications/all.json";
-        let response = self.client.get(url).send().await?;
-        let cookies_set: Vec<_> = response.cookies().collect();
-        if let Some(ct0_cookie) = cookies_set.iter().find(|c| c.name() == "ct0") {
-            if let Ok(ct0_value) = ct0_cookie.value().parse() {
-                self.headers.insert("x-csrf-token", ct0_value);
-            }
-        }
-        let client = Client::builder()
-            .cookie_provider(self.cookie_store.clone())
-            .default_headers(self.headers.clone())
-            .build()
-            .unwrap();
-        self.client = client;
-        if let Some(auth_path) = &self.auth_path {
-            let cookie_store = self.cookie_store.lock().unwrap();
-            let cookies = cookie_store.to_owned();
-            drop(cookie_store);
-            let auth = AccountAuth::new(self.headers.clone(), cookies);
-            let auth_json = serde_json::to_string(&auth).unwrap();
-            std::fs::write(auth_path, auth_json).unwrap();
-        }
-        Ok(())
+        // Another process may have refreshed already, rotating the ct0 this
+        // one holds.
+        self.reload().await?;
+        let url = self.endpoints.web(NOTIFICATIONS_PATH);
+        // Saved once below, along with the refresh time.
+        let response = self.get(&url)?.send().await?;
+        response::read(response).await?.ensure_success()?;
+        self.info.get_mut().unwrap().last_refreshed_at = Some(unix_now());
+        self.save().await
     }
 }
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::{verification::SmsCodeProvider, Account, PhoneInfo};
+
+const ADD_PHONE_PATH: &str = "/i/api/1.1/device/register.json";
+const VERIFY_PHONE_PATH: &str = "/i/api/1.1/device/register_complete.json";
+const REMOVE_PHONE_PATH: &str = "/i/api/1.1/device/unregister.json";
+
+#[derive(Deserialize)]
+struct RegisteredPhone {
+    #[serde(default)]
+    id_str: Option<String>,
+}
+
+impl Account {
+    pub async fn list_phones(&self) -> eyre::Result<Vec<PhoneInfo>> {
+        Ok(self.get_email_phone_info().await?.phone_numbers)
+    }
+
+    /// Attaches `phone_number` (E.164) once the code X texts to it comes
+    /// back from `code_provider`.
+    pub async fn add_phone(
+        &self,
+        phone_number: &str,
+        code_provider: &dyn SmsCodeProvider,
+    ) -> eyre::Result<PhoneInfo> {
+        let mut params = HashMap::new();
+        params.insert("phone_number", phone_number.to_string());
+        let request = self
+            .post(&self.endpoints.web(ADD_PHONE_PATH))?
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&params);
+        self.send(request).await?.ensure_success()?;
+
+        let prompt = format!("We sent a code to {phone_number}");
+        params.insert("numeric_pin", code_provider.sms_code(&prompt).await?);
+        let request = self
+            .post(&self.endpoints.web(VERIFY_PHONE_PATH))?
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&params);
+        let response = self.send(request).await?;
+        response
+            .ensure_success()
+            .map_err(|e| eyre::Report::new(e).wrap_err("Phone verification failed"))?;
+        let id = response
+            .json::<RegisteredPhone>()
+            .ok()
+            .and_then(|registered| registered.id_str);
+
+        // X lists numbers masked, so fall back to the visible digits.
+        self.list_phones()
+            .await?
+            .into_iter()
+            .find(|phone| match &id {
+                Some(id) => phone.id.as_ref() == Some(id),
+                None => phone.matches(phone_number),
+            })
+            .ok_or_else(|| eyre::eyre!("{phone_number} is not listed after verifying it"))
+    }
+
+    pub async fn remove_phone(&self, phone: &PhoneInfo) -> eyre::Result<()> {
+        let mut params = HashMap::new();
+        match &phone.id {
+            Some(id) => params.insert("id", id.clone()),
+            // A masked number does not say which phone to remove.
+            None if phone.is_masked() => {
+                eyre::bail!("cannot remove {} without its id", phone.phone_number)
+            }
+            None => params.insert("phone_number", phone.phone_number.clone()),
+        };
+        let request = self
+            .post(&self.endpoints.web(REMOVE_PHONE_PATH))?
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&params);
+        self.send(request).await?.ensure_success()?;
+        Ok(())
+    }
+
+    /// Detaches every phone, then checks X no longer lists any.
+    pub async fn remove_all_phones(&self) -> eyre::Result<()> {
+        for phone in self.list_phones().await? {
+            self.remove_phone(&phone).await?;
+        }
+        let remaining = self.list_phones().await?;
+        if !remaining.is_empty() {
+            eyre::bail!("{} phone(s) still attached", remaining.len());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+
+use super::{login::Login, LoginCredentials};
+
+/// Supplies credentials when an account has to log in again, e.g. from a
+/// secrets manager, so they need not be held in memory in between.
+/// A TOTP secret should come back as clones of one [`TotpConfig`], which
+/// remember the last code used; a fresh config may reuse a code X has seen.
+///
+/// [`TotpConfig`]: super::totp::TotpConfig
+#[async_trait]
+pub trait CredentialsProvider: Send + Sync {
+    async fn credentials(&self) -> eyre::Result<LoginCredentials>;
+}
+
+#[derive(Clone)]
+enum CredentialsSource {
+    Fixed(LoginCredentials),
+    Provider(Arc<dyn CredentialsProvider>),
+}
+
+/// How an [`Account`](super::Account) logs in again once X stops accepting
+/// its session: a 401 or 403 without an error code, or error code 32 or 89.
+/// The new login goes through the account's own proxy and client profile.
+#[derive(Clone)]
+pub struct ReauthPolicy {
+    credentials: CredentialsSource,
+    configure: Option<Arc<dyn Fn(Login) -> Login + Send + Sync>>,
+}
+
+impl fmt::Debug for ReauthPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let credentials = match &self.credentials {
+            CredentialsSource::Fixed(credentials) => format!("{credentials:?}"),
+            CredentialsSource::Provider(_) => "<provider>".to_string(),
+        };
+        f.debug_struct("ReauthPolicy")
+            .field("credentials", &credentials)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReauthPolicy {
+    pub fn credentials(credentials: LoginCredentials) -> Self {
+        Self {
+            credentials: CredentialsSource::Fixed(credentials),
+            configure: None,
+        }
+    }
+
+    pub fn provider(provider: impl CredentialsProvider + 'static) -> Self {
+        Self {
+            credentials: CredentialsSource::Provider(Arc::new(provider)),
+            configure: None,
+        }
+    }
+
+    /// Adjusts every re-login before it runs, e.g. to add the email code
+    /// provider or captcha solver X may ask for.
+    pub fn with_login(
+        mut self,
+        configure: impl Fn(Login) -> Login + Send + Sync + 'static,
+    ) -> Self {
+        self.configure = Some(Arc::new(configure));
+        self
+    }
+
+    pub(crate) async fn login_credentials(&self) -> eyre::Result<LoginCredentials> {
+        match &self.credentials {
+            CredentialsSource::Fixed(credentials) => Ok(credentials.clone()),
+            CredentialsSource::Provider(provider) => provider.credentials().await,
+        }
+    }
+
+    pub(crate) fn configure(&self, login: Login) -> Login {
+        match &self.configure {
+            Some(configure) => configure(login),
+            None => login,
+        }
+    }
+}
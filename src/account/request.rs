@@ -0,0 +1,166 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use reqwest::{header::SET_COOKIE, Method, Request, RequestBuilder, Url};
+use reqwest_cookie_store::CookieStore;
+
+use super::{
+    login::Login,
+    response::{self, ApiResponse},
+    Account,
+};
+
+/// What was last saved, to tell whether X changed the cookies since.
+pub(crate) struct Saved {
+    pub(crate) revision: u64,
+    cookies: u64,
+}
+
+impl Saved {
+    pub(crate) fn new(revision: u64, cookies: &CookieStore) -> Self {
+        Self {
+            revision,
+            cookies: fingerprint(cookies),
+        }
+    }
+}
+
+/// Hashes the cookies `AccountAuth` would persist.
+fn fingerprint(cookies: &CookieStore) -> u64 {
+    let mut cookies: Vec<_> = cookies
+        .iter_unexpired()
+        .filter(|cookie| cookie.is_persistent())
+        .map(|cookie| {
+            (
+                format!("{:?}", cookie.domain),
+                cookie.path.to_string(),
+                cookie.name(),
+                cookie.value(),
+            )
+        })
+        .collect();
+    cookies.sort();
+    let mut hasher = DefaultHasher::new();
+    cookies.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Account {
+    /// The `ct0` cookie X expects echoed back as the CSRF token.
+    pub(crate) fn ct0(&self) -> Option<String> {
+        self.cookie("ct0")
+    }
+
+    fn cookie(&self, name: &str) -> Option<String> {
+        let url = Url::parse(&self.endpoints.web("/")).ok()?;
+        let cookie_store = self.cookie_store.lock().unwrap();
+        let value = cookie_store
+            .get_request_values(&url)
+            .find(|(cookie, _)| *cookie == name)
+            .map(|(_, value)| value.to_string());
+        value
+    }
+
+    /// Starts a request whose CSRF header matches the live `ct0` cookie, so
+    /// it keeps up with every rotation X makes.
+    pub(crate) fn request(&self, method: Method, url: &str) -> eyre::Result<RequestBuilder> {
+        let request = self.client.request(method, Url::parse(url)?);
+        Ok(match self.ct0() {
+            Some(ct0) => request.header("x-csrf-token", ct0),
+            None => request,
+        })
+    }
+
+    pub(crate) fn get(&self, url: &str) -> eyre::Result<RequestBuilder> {
+        self.request(Method::GET, url)
+    }
+
+    pub(crate) fn post(&self, url: &str) -> eyre::Result<RequestBuilder> {
+        self.request(Method::POST, url)
+    }
+
+    /// Sends `request` and saves the session if X changed its cookies. A
+    /// failed save does not fail the request, which already went through.
+    ///
+    /// With a [`ReauthPolicy`](super::ReauthPolicy), a request rejected for
+    /// a dead session is sent once more after logging in again.
+    pub(crate) async fn send(&self, request: RequestBuilder) -> eyre::Result<ApiResponse> {
+        let request = request.build()?;
+        let retry = self.reauth.as_ref().and_then(|_| request.try_clone());
+        let auth_token = self.cookie("auth_token");
+        let response = self.execute(request).await?;
+        match retry {
+            Some(mut retry) if response.is_auth_failure() => {
+                self.reauthenticate(auth_token)
+                    .await
+                    .map_err(|e| e.wrap_err("logging in again failed"))?;
+                if let Some(ct0) = self.ct0() {
+                    retry.headers_mut().insert("x-csrf-token", ct0.parse()?);
+                }
+                self.execute(retry).await
+            }
+            _ => Ok(response),
+        }
+    }
+
+    async fn execute(&self, request: Request) -> eyre::Result<ApiResponse> {
+        let response = self.client.execute(request).await?;
+        let sets_cookies = response.headers().contains_key(SET_COOKIE);
+        let response = response::read(response).await?;
+        if sets_cookies {
+            if let Err(e) = self.save_cookies().await {
+                log::warn!("could not save rotated cookies: {e:#}");
+            }
+        }
+        Ok(response)
+    }
+
+    /// Logs in again and saves the new session over the dead one, unless a
+    /// concurrent request already did since `rejected` was turned down.
+    /// Fails without touching the session if the login is another user's.
+    async fn reauthenticate(&self, rejected: Option<String>) -> eyre::Result<()> {
+        let Some(policy) = &self.reauth else {
+            return Ok(());
+        };
+        let _reauth = self.reauth_lock.lock().await;
+        if self.cookie("auth_token") != rejected {
+            return Ok(());
+        }
+        let credentials = policy.login_credentials().await?;
+        let login = Login::new(
+            credentials,
+            self.client_config.clone(),
+            self.endpoints.clone(),
+        )?;
+        // Boxed: logging in checks the new session with a request of its own.
+        let auth = Box::pin(policy.configure(login).login()).await?;
+        // Credentials that do not name the user can log into someone else.
+        if let (Some(expected), Some(actual)) = (self.info().user_id, &auth.info().user_id) {
+            if expected != *actual {
+                eyre::bail!("logged in as user {actual} instead of {expected}");
+            }
+        }
+        *self.cookie_store.lock().unwrap() = auth.cookie_store()?;
+        *self.info.lock().unwrap() = auth.info().clone();
+        *self.user.lock().unwrap() = None;
+        // The fresh login wins over whatever was saved in the meantime.
+        while let Some(newer) = self.compare_and_swap().await? {
+            self.saved.lock().unwrap().revision = newer.revision();
+        }
+        Ok(())
+    }
+
+    /// Someone else saving first wins; their cookies replace ours.
+    pub(crate) async fn save_cookies(&self) -> eyre::Result<()> {
+        let changed = {
+            let cookie_store = self.cookie_store.lock().unwrap();
+            fingerprint(&cookie_store) != self.saved.lock().unwrap().cookies
+        };
+        if !changed {
+            return Ok(());
+        }
+        if let Some(newer) = self.compare_and_swap().await? {
+            self.replace_cookies(&newer)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::SystemTime;
+
+use reqwest::{header::DATE, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::error::{XApiError, XErrorCode};
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    errors: Vec<ErrorEntry>,
+}
+
+#[derive(Deserialize)]
+struct ErrorEntry {
+    code: u32,
+    message: String,
+}
+
+/// A fully read response, so the body is still available for error reporting
+/// after decoding fails.
+pub(crate) struct ApiResponse {
+    status: StatusCode,
+    rate_limit_reset: Option<u64>,
+    clock_offset: Option<i64>,
+    body: String,
+}
+
+pub(crate) async fn read(response: Response) -> Result<ApiResponse, reqwest::Error> {
+    let status = response.status();
+    let rate_limit_reset = response
+        .headers()
+        .get("x-rate-limit-reset")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let clock_offset = response
+        .headers()
+        .get(DATE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+        .map(
+            |server_time| match server_time.duration_since(SystemTime::now()) {
+                Ok(ahead) => ahead.as_secs() as i64,
+                Err(behind) => -(behind.duration().as_secs() as i64),
+            },
+        );
+    let body = response.text().await?;
+    Ok(ApiResponse {
+        status,
+        rate_limit_reset,
+        clock_offset,
+        body,
+    })
+}
+
+impl ApiResponse {
+    /// Seconds the server's `Date` header is ahead of the local clock.
+    pub(crate) fn clock_offset(&self) -> Option<i64> {
+        self.clock_offset
+    }
+
+    /// Whether X turned the request down because the session is dead
+    /// rather than for the request itself: codes 32 and 89, or a 401 or 403
+    /// that names no code at all.
+    pub(crate) fn is_auth_failure(&self) -> bool {
+        let code = self.error_entry().map(|entry| XErrorCode::from(entry.code));
+        match code {
+            Some(XErrorCode::BadAuthentication | XErrorCode::InvalidToken) => true,
+            Some(_) => false,
+            None => matches!(
+                self.status,
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+            ),
+        }
+    }
+
+    pub(crate) fn json<T: DeserializeOwned>(&self) -> Result<T, XApiError> {
+        if !self.status.is_success() {
+            return Err(self.error());
+        }
+        serde_json::from_str(&self.body).map_err(|e| {
+            let error = self.error();
+            if error.code.is_some() {
+                error
+            } else {
+                XApiError {
+                    message: format!("unexpected response body ({}): {}", e, self.body),
+                    ..error
+                }
+            }
+        })
+    }
+
+    pub(crate) fn ensure_success(&self) -> Result<(), XApiError> {
+        if self.status.is_success() && self.error_entry().is_none() {
+            return Ok(());
+        }
+        Err(self.error())
+    }
+
+    fn error_entry(&self) -> Option<ErrorEntry> {
+        serde_json::from_str::<ErrorBody>(&self.body)
+            .ok()
+            .and_then(|body| body.errors.into_iter().next())
+    }
+
+    fn error(&self) -> XApiError {
+        let (code, message) = match self.error_entry() {
+            Some(entry) => (Some(XErrorCode::from(entry.code)), entry.message),
+            None => (None, self.body.clone()),
+        };
+        XApiError {
+            status: self.status.as_u16(),
+            code,
+            message,
+            rate_limit_reset: self.rate_limit_reset,
+        }
+    }
+}
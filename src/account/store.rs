@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+
+use super::{encryption::AuthKey, AccountAuth};
+
+/// Where sessions live between runs, keyed by an id the caller picks, e.g.
+/// the screen name or user id.
+#[async_trait]
+pub trait AuthStore: Send + Sync {
+    async fn load(&self, account_id: &str) -> eyre::Result<Option<AccountAuth>>;
+    async fn save(&self, account_id: &str, auth: &AccountAuth) -> eyre::Result<()>;
+    async fn list(&self) -> eyre::Result<Vec<String>>;
+    /// Returns whether there was anything to delete.
+    async fn delete(&self, account_id: &str) -> eyre::Result<bool>;
+
+    /// Saves `auth` with its revision bumped, but only if the stored copy is
+    /// still at `auth.revision()`. Otherwise the newer stored copy is
+    /// returned and nothing is written.
+    ///
+    /// The default implementation is not atomic; backends that can lock
+    /// should override it.
+    async fn compare_and_swap(
+        &self,
+        account_id: &str,
+        auth: &AccountAuth,
+    ) -> eyre::Result<SaveOutcome> {
+        let current = self.load(account_id).await?;
+        let (outcome, next) = swap(current, auth);
+        if let Some(next) = next {
+            self.save(account_id, &next).await?;
+        }
+        Ok(outcome)
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveOutcome {
+    /// Written with this revision.
+    Saved(u64),
+    /// Someone else saved first; this is what they saved.
+    Stale(Box<AccountAuth>),
+}
+
+/// Decides a compare-and-swap against `current`, returning the auth to
+/// write if it goes through. A missing entry accepts any revision.
+fn swap(current: Option<AccountAuth>, auth: &AccountAuth) -> (SaveOutcome, Option<AccountAuth>) {
+    match current {
+        Some(current) if current.revision() != auth.revision() => {
+            (SaveOutcome::Stale(Box::new(current)), None)
+        }
+        _ => {
+            let next = auth.clone().with_revision(auth.revision() + 1);
+            (SaveOutcome::Saved(next.revision()), Some(next))
+        }
+    }
+}
+
+fn encode(auth: &AccountAuth, key: Option<&AuthKey>) -> eyre::Result<String> {
+    match key {
+        Some(key) => auth.encrypt(key),
+        None => Ok(serde_json::to_string(auth)?),
+    }
+}
+
+fn decode(data: &str, key: Option<&AuthKey>) -> eyre::Result<AccountAuth> {
+    match key {
+        Some(key) => AccountAuth::decrypt(data, key),
+        None => Ok(serde_json::from_str(data)?),
+    }
+}
+
+/// Ids become file names, so keep them to a safe alphabet.
+fn check_account_id(account_id: &str) -> eyre::Result<()> {
+    let valid = !account_id.is_empty()
+        && !account_id.starts_with('.')
+        && account_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '@'));
+    if !valid {
+        eyre::bail!("invalid account id {account_id:?}");
+    }
+    Ok(())
+}
+
+/// Replaces `path` with `contents` in one step: a crash leaves either the
+/// old file or the new one, never a torn write. The file is only readable
+/// by its owner.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> eyre::Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| eyre::eyre!("{} is not a file path", path.display()))?
+        .to_string_lossy();
+    let temp_path = dir.join(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        rand::random::<u32>()
+    ));
+    let write = || -> std::io::Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    };
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        eyre::Report::new(e).wrap_err(format!("failed to write {}", path.display()))
+    })
+}
+
+/// A single auth file, optionally encrypted. Writers take an advisory lock
+/// on a `.<name>.lock` file next to it, so processes sharing the file see
+/// each other's saves.
+#[derive(Clone)]
+pub(crate) struct AuthFile {
+    pub(crate) path: PathBuf,
+    pub(crate) key: Option<AuthKey>,
+}
+
+impl AuthFile {
+    pub(crate) fn load(&self) -> eyre::Result<Option<AccountAuth>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(data) => Ok(Some(decode(&data, self.key.as_ref())?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(crate) async fn save(&self, auth: &AccountAuth) -> eyre::Result<()> {
+        let auth = auth.clone();
+        self.locked(move |file| file.write(&auth)).await
+    }
+
+    pub(crate) async fn compare_and_swap(&self, auth: &AccountAuth) -> eyre::Result<SaveOutcome> {
+        let auth = auth.clone();
+        self.locked(move |file| {
+            let (outcome, next) = swap(file.load()?, &auth);
+            if let Some(next) = next {
+                file.write(&next)?;
+            }
+            Ok(outcome)
+        })
+        .await
+    }
+
+    pub(crate) async fn delete(&self) -> eyre::Result<bool> {
+        self.locked(|file| match std::fs::remove_file(&file.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        })
+        .await
+    }
+
+    /// Runs `f` holding the lock, on a blocking thread: waiting for another
+    /// process's lock, or for the disk to sync, would stall the runtime.
+    async fn locked<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Self) -> eyre::Result<T> + Send + 'static,
+    ) -> eyre::Result<T> {
+        let file = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let _lock = file.lock()?;
+            f(&file)
+        })
+        .await?
+    }
+
+    fn write(&self, auth: &AccountAuth) -> eyre::Result<()> {
+        write_atomic(&self.path, encode(auth, self.key.as_ref())?.as_bytes())
+    }
+
+    /// The auth file itself is replaced on every write, so the lock lives in
+    /// a separate file that stays put. Released when dropped.
+    fn lock(&self) -> eyre::Result<File> {
+        let file_name = self
+            .path
+            .file_name()
+            .ok_or_else(|| eyre::eyre!("{} is not a file path", self.path.display()))?
+            .to_string_lossy();
+        let lock_path = self.path.with_file_name(format!(".{file_name}.lock"));
+        let mut options = OpenOptions::new();
+        options.create(true).truncate(false).write(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let lock = options.open(&lock_path)?;
+        lock.lock()?;
+        Ok(lock)
+    }
+}
+
+/// One `<account_id>.json` file per account in a directory.
+pub struct FileAuthStore {
+    dir: PathBuf,
+    key: Option<AuthKey>,
+}
+
+impl FileAuthStore {
+    pub fn new<P: AsRef<Path>>(dir: P) -> eyre::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, key: None })
+    }
+
+    /// Encrypts every file with `key`.
+    pub fn with_key(mut self, key: AuthKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    fn file(&self, account_id: &str) -> eyre::Result<AuthFile> {
+        check_account_id(account_id)?;
+        Ok(AuthFile {
+            path: self.dir.join(format!("{account_id}.json")),
+            key: self.key.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl AuthStore for FileAuthStore {
+    async fn load(&self, account_id: &str) -> eyre::Result<Option<AccountAuth>> {
+        self.file(account_id)?.load()
+    }
+
+    async fn save(&self, account_id: &str, auth: &AccountAuth) -> eyre::Result<()> {
+        self.file(account_id)?.save(auth).await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        account_id: &str,
+        auth: &AccountAuth,
+    ) -> eyre::Result<SaveOutcome> {
+        self.file(account_id)?.compare_and_swap(auth).await
+    }
+
+    async fn list(&self) -> eyre::Result<Vec<String>> {
+        let mut account_ids = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(account_id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    if check_account_id(account_id).is_ok() {
+                        account_ids.push(account_id.to_string());
+                    }
+                }
+            }
+        }
+        account_ids.sort();
+        Ok(account_ids)
+    }
+
+    async fn delete(&self, account_id: &str) -> eyre::Result<bool> {
+        self.file(account_id)?.delete().await
+    }
+}
+
+/// Keeps sessions in memory only, e.g. for tests or short-lived workers.
+#[derive(Default)]
+pub struct MemoryAuthStore {
+    auths: Mutex<HashMap<String, AccountAuth>>,
+}
+
+impl MemoryAuthStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AuthStore for MemoryAuthStore {
+    async fn load(&self, account_id: &str) -> eyre::Result<Option<AccountAuth>> {
+        Ok(self.auths.lock().unwrap().get(account_id).cloned())
+    }
+
+    async fn save(&self, account_id: &str, auth: &AccountAuth) -> eyre::Result<()> {
+        self.auths
+            .lock()
+            .unwrap()
+            .insert(account_id.to_string(), auth.clone());
+        Ok(())
+    }
+
+    async fn list(&self) -> eyre::Result<Vec<String>> {
+        let mut account_ids: Vec<String> = self.auths.lock().unwrap().keys().cloned().collect();
+        account_ids.sort();
+        Ok(account_ids)
+    }
+
+    async fn delete(&self, account_id: &str) -> eyre::Result<bool> {
+        Ok(self.auths.lock().unwrap().remove(account_id).is_some())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        account_id: &str,
+        auth: &AccountAuth,
+    ) -> eyre::Result<SaveOutcome> {
+        let mut auths = self.auths.lock().unwrap();
+        let (outcome, next) = swap(auths.get(account_id).cloned(), auth);
+        if let Some(next) = next {
+            auths.insert(account_id.to_string(), next);
+        }
+        Ok(outcome)
+    }
+}
+
+/// All sessions in one SQLite table, `account_auth`. The user id and screen
+/// name get their own columns so the fleet can be queried directly. Needs
+/// the `sqlite` feature, or `sqlite-bundled` to compile SQLite in.
+/// Clones share the connection.
+#[cfg(feature = "sqlite")]
+#[derive(Clone)]
+pub struct SqliteAuthStore {
+    connection: std::sync::Arc<Mutex<rusqlite::Connection>>,
+    key: Option<AuthKey>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteAuthStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> eyre::Result<Self> {
+        Self::new(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> eyre::Result<Self> {
+        Self::new(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn new(connection: rusqlite::Connection) -> eyre::Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS account_auth (
+                account_id TEXT PRIMARY KEY,
+                user_id TEXT,
+                screen_name TEXT,
+                updated_at INTEGER NOT NULL,
+                auth TEXT NOT NULL
+            )",
+        )?;
+        Ok(Self {
+            connection: std::sync::Arc::new(Mutex::new(connection)),
+            key: None,
+        })
+    }
+
+    /// Encrypts the `auth` column with `key`.
+    pub fn with_key(mut self, key: AuthKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteAuthStore {
+    /// Runs `f` on the connection on a blocking thread: SQLite blocks on
+    /// disk, and for up to its busy timeout while another process holds
+    /// the database.
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Self, &mut rusqlite::Connection) -> eyre::Result<T> + Send + 'static,
+    ) -> eyre::Result<T> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = store.connection.lock().unwrap();
+            f(&store, &mut connection)
+        })
+        .await?
+    }
+
+    fn load_with(
+        &self,
+        connection: &rusqlite::Connection,
+        account_id: &str,
+    ) -> eyre::Result<Option<AccountAuth>> {
+        use rusqlite::OptionalExtension;
+
+        let data: Option<String> = connection
+            .query_row(
+                "SELECT auth FROM account_auth WHERE account_id = ?1",
+                [account_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        data.map(|data| decode(&data, self.key.as_ref()))
+            .transpose()
+    }
+
+    fn save_with(
+        &self,
+        connection: &rusqlite::Connection,
+        account_id: &str,
+        auth: &AccountAuth,
+    ) -> eyre::Result<()> {
+        let data = encode(auth, self.key.as_ref())?;
+        let info = auth.info();
+        connection.execute(
+            "INSERT INTO account_auth (account_id, user_id, screen_name, updated_at, auth)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (account_id) DO UPDATE SET
+                user_id = excluded.user_id,
+                screen_name = excluded.screen_name,
+                updated_at = excluded.updated_at,
+                auth = excluded.auth",
+            rusqlite::params![
+                account_id,
+                info.user_id,
+                info.screen_name,
+                super::unix_now() as i64,
+                data
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait]
+impl AuthStore for SqliteAuthStore {
+    async fn load(&self, account_id: &str) -> eyre::Result<Option<AccountAuth>> {
+        let account_id = account_id.to_string();
+        self.blocking(move |store, connection| store.load_with(connection, &account_id))
+            .await
+    }
+
+    async fn save(&self, account_id: &str, auth: &AccountAuth) -> eyre::Result<()> {
+        let (account_id, auth) = (account_id.to_string(), auth.clone());
+        self.blocking(move |store, connection| store.save_with(connection, &account_id, &auth))
+            .await
+    }
+
+    async fn list(&self) -> eyre::Result<Vec<String>> {
+        self.blocking(|_, connection| {
+            let mut statement =
+                connection.prepare("SELECT account_id FROM account_auth ORDER BY account_id")?;
+            let account_ids = statement
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok(account_ids)
+        })
+        .await
+    }
+
+    async fn delete(&self, account_id: &str) -> eyre::Result<bool> {
+        let account_id = account_id.to_string();
+        self.blocking(move |_, connection| {
+            let deleted = connection.execute(
+                "DELETE FROM account_auth WHERE account_id = ?1",
+                [account_id],
+            )?;
+            Ok(deleted > 0)
+        })
+        .await
+    }
+
+    /// Runs in an immediate transaction, which also keeps other processes
+    /// using the same database out.
+    async fn compare_and_swap(
+        &self,
+        account_id: &str,
+        auth: &AccountAuth,
+    ) -> eyre::Result<SaveOutcome> {
+        let (account_id, auth) = (account_id.to_string(), auth.clone());
+        self.blocking(move |store, connection| {
+            let transaction =
+                connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            let (outcome, next) = swap(store.load_with(&transaction, &account_id)?, &auth);
+            if let Some(next) = next {
+                store.save_with(&transaction, &account_id, &next)?;
+            }
+            transaction.commit()?;
+            Ok(outcome)
+        })
+        .await
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Url;
+pub use totp_rs::Algorithm;
+use totp_rs::{Secret, TOTP};
+use zeroize::Zeroizing;
+
+use super::LoginError;
+
+/// How to generate codes for `LoginTwoFactorAuthChallenge`. The defaults
+/// match the secrets X hands out: SHA1, 6 digits, 30 second periods.
+///
+/// Clones remember the same last used code, so logins built from one
+/// config, e.g. by a [`ReauthPolicy`](super::ReauthPolicy), never submit a
+/// code twice.
+#[derive(Clone)]
+pub struct TotpConfig {
+    secret: Zeroizing<Vec<u8>>,
+    algorithm: Algorithm,
+    digits: usize,
+    period: u64,
+    last_step: Arc<Mutex<Option<u64>>>,
+}
+
+impl TotpConfig {
+    /// Accepts either a base32 secret or an `otpauth://totp/` URI.
+    pub fn new(secret: &str) -> Result<Self, LoginError> {
+        let secret = secret.trim();
+        if secret.starts_with("otpauth://") {
+            return Self::from_uri(secret);
+        }
+        Ok(Self {
+            secret: decode_secret(secret)?,
+            algorithm: Algorithm::SHA1,
+            digits: 6,
+            period: 30,
+            last_step: Default::default(),
+        })
+    }
+
+    /// Reads the secret and any `algorithm`, `digits` and `period`
+    /// parameters from an `otpauth://totp/` URI.
+    pub fn from_uri(uri: &str) -> Result<Self, LoginError> {
+        let url = Url::parse(uri).map_err(|e| invalid(format!("bad otpauth URI: {e}")))?;
+        if url.scheme() != "otpauth" || url.host_str() != Some("totp") {
+            return Err(invalid("expected an otpauth://totp/ URI"));
+        }
+        let mut secret = None;
+        let mut algorithm = Algorithm::SHA1;
+        let mut digits = 6;
+        let mut period = 30;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "secret" => secret = Some(decode_secret(&value)?),
+                "algorithm" => algorithm = parse_algorithm(&value)?,
+                "digits" => digits = value.parse().map_err(|_| invalid("bad digits"))?,
+                "period" => period = value.parse().map_err(|_| invalid("bad period"))?,
+                _ => {}
+            }
+        }
+        let secret = secret.ok_or_else(|| invalid("otpauth URI has no secret"))?;
+        Self {
+            secret,
+            algorithm,
+            digits: 6,
+            period: 30,
+            last_step: Default::default(),
+        }
+        .with_digits(digits)?
+        .with_period(period)
+    }
+
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_digits(mut self, digits: usize) -> Result<Self, LoginError> {
+        if !(6..=8).contains(&digits) {
+            return Err(invalid(format!("{digits} digits, expected 6 to 8")));
+        }
+        self.digits = digits;
+        Ok(self)
+    }
+
+    pub fn with_period(mut self, period: u64) -> Result<Self, LoginError> {
+        if period == 0 {
+            return Err(invalid("period must be at least one second"));
+        }
+        self.period = period;
+        Ok(self)
+    }
+}
+
+impl fmt::Debug for TotpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpConfig")
+            .field("secret", &"<redacted>")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .finish()
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> LoginError {
+    LoginError::InvalidTotpSecret {
+        reason: reason.into(),
+    }
+}
+
+fn decode_secret(secret: &str) -> Result<Zeroizing<Vec<u8>>, LoginError> {
+    // Authenticator apps show secrets grouped, lowercased or padded.
+    let secret: Zeroizing<String> = Zeroizing::new(
+        secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=' && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect(),
+    );
+    let bytes = Zeroizing::new(
+        Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|_| invalid("secret is not valid base32"))?,
+    );
+    if bytes.is_empty() {
+        return Err(invalid("secret is empty"));
+    }
+    Ok(bytes)
+}
+
+fn parse_algorithm(name: &str) -> Result<Algorithm, LoginError> {
+    match name.to_uppercase().as_str() {
+        "SHA1" => Ok(Algorithm::SHA1),
+        "SHA256" => Ok(Algorithm::SHA256),
+        "SHA512" => Ok(Algorithm::SHA512),
+        other => Err(invalid(format!("unsupported algorithm {other}"))),
+    }
+}
+
+/// Generates codes from a [`TotpConfig`], never handing out the code for a
+/// period it already used; X rejects a code it has seen before.
+pub(crate) struct TotpGenerator {
+    totp: TOTP,
+    last_step: Arc<Mutex<Option<u64>>>,
+}
+
+impl TotpGenerator {
+    pub(crate) fn new(config: TotpConfig) -> Self {
+        Self {
+            totp: TOTP::new_unchecked(
+                config.algorithm,
+                config.digits,
+                1,
+                config.period,
+                config.secret.to_vec(),
+            ),
+            last_step: config.last_step,
+        }
+    }
+
+    /// The code for the current period by the server's clock, `clock_offset`
+    /// seconds ahead of ours. Waits for the next period if this one's code
+    /// was already used.
+    pub(crate) async fn code(&self, clock_offset: i64) -> String {
+        let period_ms = self.totp.step * 1000;
+        loop {
+            let local_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64;
+            let now_ms = (local_ms + clock_offset * 1000).max(0) as u64;
+            let step = now_ms / period_ms;
+            {
+                let mut last_step = self.last_step.lock().unwrap();
+                if *last_step != Some(step) {
+                    *last_step = Some(step);
+                    return self.totp.generate(now_ms / 1000);
+                }
+            }
+            let wait = period_ms - now_ms % period_ms;
+            tokio::time::sleep(Duration::from_millis(wait)).await;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::Account;
+
+const VERIFY_CREDENTIALS_PATH: &str = "/1.1/account/verify_credentials.json";
+
+/// The user a session belongs to, as `verify_credentials` reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UserProfile {
+    #[serde(rename = "id_str")]
+    pub user_id: String,
+    pub screen_name: String,
+    pub name: String,
+    /// Seconds since the epoch.
+    #[serde(deserialize_with = "x_timestamp")]
+    pub created_at: u64,
+    #[serde(rename = "followers_count", default)]
+    pub followers: u64,
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default)]
+    pub verified: bool,
+    #[serde(default)]
+    pub suspended: bool,
+    /// Locked until a phone number is verified. Accounts X locks outright
+    /// fail with [`XErrorCode::Locked`](super::XErrorCode::Locked) instead.
+    #[serde(rename = "needs_phone_verification", default)]
+    pub locked: bool,
+}
+
+/// Reads X's `Wed Oct 10 20:19:24 +0000 2018` timestamps, which are always
+/// in UTC, by rearranging them into an HTTP date.
+fn x_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    use serde::de::Error;
+
+    let value = String::deserialize(deserializer)?;
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [weekday, month, day, time, "+0000", year] = parts[..] else {
+        return Err(D::Error::custom(format!("unexpected timestamp {value:?}")));
+    };
+    let http_date = format!("{weekday}, {day:0>2} {month} {year} {time} GMT");
+    let time = httpdate::parse_http_date(&http_date).map_err(D::Error::custom)?;
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(D::Error::custom)?;
+    Ok(since_epoch.as_secs())
+}
+
+impl Account {
+    /// Asks X who the session belongs to, which also checks that it is
+    /// still alive. The answer is kept for [`me`](Self::me).
+    pub async fn verify_credentials(&self) -> eyre::Result<UserProfile> {
+        let url = self.endpoints.api(VERIFY_CREDENTIALS_PATH);
+        let profile: UserProfile = self.send(self.get(&url)?).await?.json()?;
+        *self.user.lock().unwrap() = Some(profile.clone());
+        Ok(profile)
+    }
+
+    /// The session's user, only asking X the first time.
+    pub async fn me(&self) -> eyre::Result<UserProfile> {
+        let cached = self.user.lock().unwrap().clone();
+        match cached {
+            Some(profile) => Ok(profile),
+            None => self.verify_credentials().await,
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Supplies the one-time code X mails out when it asks for email
+/// confirmation during login.
+#[async_trait]
+pub trait EmailCodeProvider: Send + Sync {
+    async fn email_code(&self, email: &str) -> eyre::Result<String>;
+}
+
+/// Receives codes pushed from elsewhere in the process, e.g. a web handler
+/// where an operator pastes the code.
+pub struct ChannelCodeProvider {
+    receiver: AsyncMutex<mpsc::UnboundedReceiver<String>>,
+    timeout: Duration,
+}
+
+impl ChannelCodeProvider {
+    pub fn new() -> (mpsc::UnboundedSender<String>, Self) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let provider = Self {
+            receiver: AsyncMutex::new(receiver),
+            timeout: DEFAULT_TIMEOUT,
+        };
+        (sender, provider)
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn next_code(&self, kind: &str) -> eyre::Result<String> {
+        let mut receiver = self.receiver.lock().await;
+        let code = tokio::time::timeout(self.timeout, receiver.recv())
+            .await
+            .map_err(|_| eyre::eyre!("timed out waiting for {} code", kind))?
+            .ok_or_else(|| eyre::eyre!("{} code channel closed", kind))?;
+        Ok(code.trim().to_string())
+    }
+}
+
+#[async_trait]
+impl EmailCodeProvider for ChannelCodeProvider {
+    async fn email_code(&self, _email: &str) -> eyre::Result<String> {
+        self.next_code("email").await
+    }
+}
+
+#[async_trait]
+impl SmsCodeProvider for ChannelCodeProvider {
+    async fn sms_code(&self, _prompt: &str) -> eyre::Result<String> {
+        self.next_code("SMS").await
+    }
+}
+
+/// Supplies the code X texts to the account's phone when its two-factor
+/// method is SMS. `prompt` is X's note on where the code went, e.g. "We sent
+/// a code to your phone number ending in 42".
+#[async_trait]
+pub trait SmsCodeProvider: Send + Sync {
+    async fn sms_code(&self, prompt: &str) -> eyre::Result<String>;
+}
+
+/// The one-time backup codes from X's two-factor settings. Each code is
+/// handed out at most once; [`BackupCodes::used`] tells which ones a login
+/// spent so they can be struck off wherever they are kept.
+#[derive(Debug, Default)]
+pub struct BackupCodes {
+    codes: Mutex<(VecDeque<String>, Vec<String>)>,
+}
+
+impl BackupCodes {
+    pub fn new<I, S>(codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let remaining = codes
+            .into_iter()
+            .map(|code| code.into().trim().to_string())
+            .filter(|code| !code.is_empty())
+            .collect();
+        Self {
+            codes: Mutex::new((remaining, Vec::new())),
+        }
+    }
+
+    pub(crate) fn take(&self) -> Option<String> {
+        let mut codes = self.codes.lock().unwrap();
+        let (remaining, used) = &mut *codes;
+        let code = remaining.pop_front()?;
+        used.push(code.clone());
+        Some(code)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.lock().unwrap().0.is_empty()
+    }
+
+    pub fn remaining(&self) -> Vec<String> {
+        self.codes.lock().unwrap().0.iter().cloned().collect()
+    }
+
+    pub fn used(&self) -> Vec<String> {
+        self.codes.lock().unwrap().1.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailboxFormat {
+    Maildir,
+    Mbox,
+}
+
+/// Polls a local mailbox for X's confirmation email, newest first. Only
+/// mail sent from an X address to the account's email (by `To:` or
+/// `Delivered-To:`) counts, so one catch-all mailbox can serve many
+/// accounts. A code is never handed out twice.
+pub struct MailboxCodeProvider {
+    path: PathBuf,
+    format: MailboxFormat,
+    timeout: Duration,
+    poll_interval: Duration,
+    max_age: Duration,
+    used: Mutex<HashSet<String>>,
+}
+
+impl MailboxCodeProvider {
+    pub fn maildir<P: AsRef<Path>>(path: P) -> Self {
+        Self::new(path, MailboxFormat::Maildir)
+    }
+
+    pub fn mbox<P: AsRef<Path>>(path: P) -> Self {
+        Self::new(path, MailboxFormat::Mbox)
+    }
+
+    fn new<P: AsRef<Path>>(path: P, format: MailboxFormat) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            format,
+            timeout: DEFAULT_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_age: DEFAULT_MAX_AGE,
+            used: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Messages older than this are ignored: by modification time for
+    /// Maildir, by the `Date:` header for mbox.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    fn messages(&self) -> eyre::Result<Vec<String>> {
+        match self.format {
+            MailboxFormat::Maildir => self.maildir_messages(),
+            MailboxFormat::Mbox => self.mbox_messages(),
+        }
+    }
+
+    fn mbox_messages(&self) -> eyre::Result<Vec<String>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let oldest = SystemTime::now() - self.max_age;
+        let mbox = std::fs::read_to_string(&self.path)?;
+        // Mail whose age cannot be told is skipped along with old mail.
+        let mut messages: Vec<(SystemTime, String)> = split_mbox(&mbox)
+            .into_iter()
+            .filter_map(|message| Some((message_date(&message)?, message)))
+            .filter(|(date, _)| *date >= oldest)
+            .collect();
+        // Later in the file wins a tie.
+        messages.reverse();
+        messages.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+        Ok(messages.into_iter().map(|(_, message)| message).collect())
+    }
+
+    fn maildir_messages(&self) -> eyre::Result<Vec<String>> {
+        let oldest = SystemTime::now() - self.max_age;
+        let mut files = Vec::new();
+        for sub in ["new", "cur"] {
+            let dir = self.path.join(sub);
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let modified = entry.metadata()?.modified()?;
+                if modified >= oldest {
+                    files.push((modified, entry.path()));
+                }
+            }
+        }
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        let mut messages = Vec::new();
+        for (_, path) in files {
+            messages.push(String::from_utf8_lossy(&std::fs::read(path)?).into_owned());
+        }
+        Ok(messages)
+    }
+
+    fn find_code(&self, email: &str) -> eyre::Result<Option<String>> {
+        let mut used = self.used.lock().unwrap();
+        for message in self.messages()? {
+            if !is_from_x(&message) || !is_addressed_to(&message, email) {
+                continue;
+            }
+            if let Some(code) = extract_code(&message) {
+                if used.insert(code.clone()) {
+                    return Ok(Some(code));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl EmailCodeProvider for MailboxCodeProvider {
+    async fn email_code(&self, email: &str) -> eyre::Result<String> {
+        if email.is_empty() {
+            eyre::bail!("the account's email is needed to find its mail");
+        }
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            if let Some(code) = self.find_code(email)? {
+                return Ok(code);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                eyre::bail!("no confirmation code for {} in {:?}", email, self.path);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+fn split_mbox(mbox: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in mbox.lines() {
+        if line.starts_with("From ") && !current.is_empty() {
+            messages.push(std::mem::take(&mut current));
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+/// The values of every `name:` header, ignoring case.
+fn headers<'a>(message: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> {
+    message
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .filter(move |(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+fn header<'a>(message: &'a str, name: &'a str) -> Option<&'a str> {
+    headers(message, name).next()
+}
+
+/// `info@x.com` out of `X <info@x.com>`.
+fn address(mailbox: &str) -> &str {
+    let address = match mailbox.rsplit_once('<') {
+        Some((_, address)) => address.trim_end_matches('>'),
+        None => mailbox,
+    };
+    address.trim()
+}
+
+fn is_addressed_to(message: &str, email: &str) -> bool {
+    ["to", "delivered-to"]
+        .iter()
+        .flat_map(|name| headers(message, name))
+        .flat_map(|value| value.split(','))
+        .any(|mailbox| address(mailbox).eq_ignore_ascii_case(email.trim()))
+}
+
+fn is_from_x(message: &str) -> bool {
+    let Some(from) = header(message, "from") else {
+        return false;
+    };
+    let Some((_, domain)) = address(from).rsplit_once('@') else {
+        return false;
+    };
+    let domain = domain.to_ascii_lowercase();
+    ["x.com", "twitter.com"]
+        .iter()
+        .any(|x| domain == *x || domain.ends_with(&format!(".{x}")))
+}
+
+/// When a message was sent, from its `Date:` header or, failing that, the
+/// mbox `From ` line, which is in UTC.
+fn message_date(message: &str) -> Option<SystemTime> {
+    if let Some(date) = header(message, "date").and_then(parse_rfc2822_date) {
+        return Some(date);
+    }
+    let from_line = message.lines().next()?.strip_prefix("From ")?;
+    // `From sender Tue Oct 15 10:00:00 2024`
+    let fields: Vec<&str> = from_line.split_whitespace().collect();
+    let [.., _weekday, month, day, time, year] = fields[..] else {
+        return None;
+    };
+    utc_time(year, month, day, time, 0)
+}
+
+/// Reads `Tue, 15 Oct 2024 10:00:00 +0200 (CEST)`.
+fn parse_rfc2822_date(date: &str) -> Option<SystemTime> {
+    let date = date.split_once(',').map_or(date, |(_, date)| date);
+    let fields: Vec<&str> = date.split_whitespace().collect();
+    let [day, month, year, time, zone, ..] = fields[..] else {
+        return None;
+    };
+    let offset = match zone {
+        "GMT" | "UTC" | "UT" | "Z" => 0,
+        _ => {
+            let sign = match zone.get(..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let hours: i64 = zone.get(1..3)?.parse().ok()?;
+            let minutes: i64 = zone.get(3..5)?.parse().ok()?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+    utc_time(year, month, day, time, offset)
+}
+
+/// `offset` is how many seconds the given local time is ahead of UTC.
+fn utc_time(year: &str, month: &str, day: &str, time: &str, offset: i64) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let day: i64 = day.parse().ok()?;
+    let mut clock = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute) = (clock.next()??, clock.next()??);
+    let second = clock.next().flatten().unwrap_or(0);
+    let seconds =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds.try_into().ok()?))
+}
+
+/// Days from 1970-01-01 to the given proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Pulls the code out of X's "Your X confirmation code is ..." mail, falling
+/// back to the first standalone 6-8 digit number in the body.
+fn extract_code(message: &str) -> Option<String> {
+    let lower = message.to_ascii_lowercase();
+    for marker in ["confirmation code is", "verification code is"] {
+        if let Some(index) = lower.find(marker) {
+            let code: String = message[index + marker.len()..]
+                .trim_start()
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+            if !code.is_empty() {
+                return Some(code);
+            }
+        }
+    }
+    let body = message.split_once("\n\n").map_or(message, |(_, body)| body);
+    body.split(|c: char| !c.is_ascii_alphanumeric())
+        .find(|word| (6..=8).contains(&word.len()) && word.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+}
@@ -0,0 +1,825 @@
+mod common;
+
+use common::{password_script, MockAccount, MockX};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde_json::json;
+#[cfg(feature = "sqlite")]
+use x_rs::account::SqliteAuthStore;
+use x_rs::account::{
+    login::Login, verification::ChannelCodeProvider, Account, AccountAuth, AuthKey, AuthStore,
+    ClientConfig, ClientProfile, CredentialsProvider, Endpoints, FileAuthStore, LoginCredentials,
+    MemoryAuthStore, ReauthPolicy, SaveOutcome, UserProfile, XApiError, XErrorCode, AUTH_VERSION,
+};
+
+#[tokio::test]
+async fn email_phone_info_requires_session() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let account = mock.account().await;
+    let info = account.get_email_phone_info().await.unwrap();
+    assert!(info.emails[0].email_verified);
+    assert!(info.phone_numbers.is_empty());
+
+    mock.state().auth_token = Some("revoked".to_string());
+    let error = account.get_email_phone_info().await.unwrap_err();
+    let error = error.downcast_ref::<XApiError>().unwrap();
+    assert_eq!(error.status, 401);
+    assert_eq!(error.code, Some(XErrorCode::BadAuthentication));
+    assert!(error.is_auth_failure());
+}
+
+#[tokio::test]
+async fn email_phone_info_lists_every_attachment() {
+    let account = MockAccount {
+        emails: vec![
+            json!({"email": common::EMAIL, "email_verified": true, "primary": true}),
+            json!({"email": "new@example.com", "email_verified": false, "pending": true}),
+        ],
+        phone_numbers: vec![
+            json!({
+                "id": 1234567890,
+                "phone_number": common::PHONE,
+                "phone_number_verified": true,
+                "carrier": "Mock Mobile",
+                "country_code": "US",
+            }),
+            json!({"id_str": "42", "phone_number": "+4********89"}),
+            json!({
+                "id": 1_606_050_400_000_000_001_u64,
+                "id_str": "1606050400000000001",
+                "phone_number": "+1*******23",
+                "phone_number_verified": true,
+            }),
+        ],
+        ..Default::default()
+    };
+    let mock = MockX::start(account, password_script()).await;
+    let info = mock.account().await.get_email_phone_info().await.unwrap();
+
+    assert!(info.emails[0].primary && !info.emails[0].pending);
+    assert!(!info.emails[1].primary && info.emails[1].pending);
+    let [phone, masked, both_ids] = &info.phone_numbers[..] else {
+        panic!("{info:?}");
+    };
+    assert_eq!(phone.id.as_deref(), Some("1234567890"));
+    assert_eq!(phone.phone_number, common::PHONE);
+    assert!(phone.verified && !phone.is_masked());
+    assert_eq!(phone.carrier.as_deref(), Some("Mock Mobile"));
+    assert_eq!(phone.country_code.as_deref(), Some("US"));
+    assert_eq!(masked.id.as_deref(), Some("42"));
+    assert!(!masked.verified && masked.is_masked());
+    assert_eq!(masked.carrier, None);
+    assert_eq!(both_ids.id.as_deref(), Some("1606050400000000001"));
+    assert!(both_ids.verified);
+}
+
+#[tokio::test]
+async fn list_and_revoke_oauth_applications() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let account = mock.account().await;
+
+    let applications = account.get_all_oauth_applications().await.unwrap();
+    assert_eq!(applications.len(), 2);
+    account
+        .revoke_oauth_application(&applications[0].token)
+        .await
+        .unwrap();
+    assert_eq!(mock.state().revoked, ["token-1"]);
+    let error = account
+        .revoke_oauth_application("missing")
+        .await
+        .unwrap_err();
+    let error = error.downcast_ref::<XApiError>().unwrap();
+    assert_eq!(error.code, Some(XErrorCode::Other(34)));
+
+    account.revoke_all_oauth_applications().await.unwrap();
+    assert!(account
+        .get_all_oauth_applications()
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn change_password_checks_current_password() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let account = mock.account().await;
+
+    let error = account
+        .change_password("wrong", "new-password")
+        .await
+        .unwrap_err();
+    let api_error = error.downcast_ref::<XApiError>().unwrap();
+    assert_eq!(api_error.code, Some(XErrorCode::Other(114)));
+    assert_eq!(api_error.message, "The password you entered was incorrect.");
+    account
+        .change_password(common::PASSWORD, "new-password")
+        .await
+        .unwrap();
+    assert_eq!(mock.state().account.password, "new-password");
+}
+
+#[tokio::test]
+async fn refresh_cookies_follows_rotated_ct0_and_persists() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let path = std::env::temp_dir().join(format!("x-rs-auth-{}.json", mock.addr.port()));
+    std::fs::write(&path, serde_json::to_string(&auth).unwrap()).unwrap();
+
+    let mut account = Account::from_file(&path, mock.endpoints()).unwrap();
+    account.refresh_cookies().await.unwrap();
+    account.get_email_phone_info().await.unwrap();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let ct0 = mock.state().ct0.clone().unwrap();
+    assert_eq!(saved["headers"]["x-csrf-token"], json!(ct0));
+}
+
+#[tokio::test]
+async fn from_auth_token_fetches_missing_ct0() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    mock.state().auth_token = Some("auth-from-browser".to_string());
+
+    let account = Account::from_auth_token(
+        "auth-from-browser",
+        None,
+        ClientConfig::default(),
+        mock.endpoints(),
+    )
+    .await
+    .unwrap();
+    let ct0 = mock.state().ct0.clone().unwrap();
+    let cookies: serde_json::Value = serde_json::from_str(&account.auth_cookie_string()).unwrap();
+    assert_eq!(
+        cookies,
+        json!({"auth_token": "auth-from-browser", "ct0": ct0})
+    );
+    account.get_all_oauth_applications().await.unwrap();
+
+    let account = Account::from_auth_token(
+        "auth-from-browser",
+        Some(&ct0),
+        ClientConfig::default(),
+        mock.endpoints(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(mock.state().ct0.as_deref(), Some(ct0.as_str()));
+    account.get_email_phone_info().await.unwrap();
+}
+
+#[tokio::test]
+async fn from_auth_token_rejects_dead_session() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    mock.state().auth_token = Some("auth-live".to_string());
+
+    let error = Account::from_auth_token(
+        "auth-expired",
+        None,
+        ClientConfig::default(),
+        mock.endpoints(),
+    )
+    .await
+    .err()
+    .unwrap();
+    let error = error.downcast_ref::<XApiError>().unwrap();
+    assert_eq!(error.code, Some(XErrorCode::BadAuthentication));
+}
+
+#[test]
+fn browser_cookie_exports_round_trip() {
+    let cookies_txt = "# Netscape HTTP Cookie File
+#HttpOnly_.x.com\tTRUE\t/\tTRUE\t4102444800\tauth_token\tauth-from-browser
+.x.com\tTRUE\t/\tTRUE\t4102444800\tct0\tct0-from-browser
+twitter.com\tFALSE\t/i\tFALSE\t4102444800\tguest_id\tv1%3A123
+.google.com\tTRUE\t/\tTRUE\t4102444800\tNID\tforeign
+.x.com\tTRUE\t/\tTRUE\t946684800\tkdt\texpired
+";
+    let auth = AccountAuth::from_netscape(cookies_txt).unwrap();
+    let stored = serde_json::to_value(&auth).unwrap();
+    assert_eq!(stored["headers"]["x-csrf-token"], json!("ct0-from-browser"));
+
+    let exported: Vec<serde_json::Value> =
+        serde_json::from_str(&auth.to_browser_json().unwrap()).unwrap();
+    let mut names: Vec<_> = exported
+        .iter()
+        .map(|cookie| cookie["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["auth_token", "ct0", "guest_id"]);
+    let auth_token = exported
+        .iter()
+        .find(|cookie| cookie["name"] == "auth_token")
+        .unwrap();
+    assert_eq!(auth_token["domain"], json!(".x.com"));
+    assert_eq!(auth_token["httpOnly"], json!(true));
+    assert_eq!(auth_token["secure"], json!(true));
+    assert_eq!(auth_token["expirationDate"], json!(4102444800.0));
+    let guest_id = exported
+        .iter()
+        .find(|cookie| cookie["name"] == "guest_id")
+        .unwrap();
+    assert_eq!(guest_id["hostOnly"], json!(true));
+    assert_eq!(guest_id["path"], json!("/i"));
+
+    let reimported = AccountAuth::from_browser_json(&auth.to_browser_json().unwrap()).unwrap();
+    let mut lines: Vec<_> = reimported
+        .to_netscape()
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    let mut expected: Vec<_> = cookies_txt.lines().take(4).map(str::to_string).collect();
+    expected.sort();
+    assert_eq!(lines, expected);
+}
+
+#[tokio::test]
+async fn auth_records_who_and_when() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    assert_eq!(auth.version(), AUTH_VERSION);
+    let info = auth.info();
+    assert_eq!(info.user_id.as_deref(), Some(common::USER_ID));
+    assert_eq!(info.screen_name.as_deref(), Some(common::USERNAME));
+    assert!(info.created_at.is_some());
+    assert!(auth.client().profile().is_some());
+    assert_eq!(info.last_refreshed_at, None);
+}
+
+#[tokio::test]
+async fn v0_auth_file_is_migrated_on_load() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = serde_json::to_value(mock.login_auth().await).unwrap();
+    let v0 = json!({"headers": auth["headers"], "cookies": auth["cookies"]});
+    let path = std::env::temp_dir().join(format!("x-rs-auth-v0-{}.json", mock.addr.port()));
+    std::fs::write(&path, v0.to_string()).unwrap();
+
+    let mut account = Account::from_file(&path, mock.endpoints()).unwrap();
+    assert_eq!(account.info().user_id.as_deref(), Some(common::USER_ID));
+    assert_eq!(account.info().created_at, None);
+    account.refresh_cookies().await.unwrap();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(saved["version"], json!(AUTH_VERSION));
+    assert_eq!(saved["user_id"], json!(common::USER_ID));
+    assert!(saved["last_refreshed_at"].is_u64());
+    assert_eq!(
+        saved["client"]["profile"]["user_agent"],
+        auth["headers"]["user-agent"]
+    );
+
+    let future = json!({"version": AUTH_VERSION + 1, "headers": {}, "cookies": ""});
+    assert!(serde_json::from_value::<AccountAuth>(future).is_err());
+}
+
+#[tokio::test]
+async fn encrypted_auth_file_stays_encrypted_on_refresh() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let auth_token = mock.state().auth_token.clone().unwrap();
+    let key = AuthKey::passphrase("hunter2 hunter2");
+    let path = std::env::temp_dir().join(format!("x-rs-auth-enc-{}.json", mock.addr.port()));
+    std::fs::write(&path, auth.encrypt(&key).unwrap()).unwrap();
+
+    let mut account = Account::from_encrypted_file(&path, key.clone(), mock.endpoints()).unwrap();
+    account.refresh_cookies().await.unwrap();
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(!saved.contains(&auth_token));
+    assert!(serde_json::from_str::<serde_json::Value>(&saved).unwrap()["ciphertext"].is_string());
+    let refreshed = AccountAuth::decrypt(&saved, &key).unwrap();
+    assert!(refreshed.info().last_refreshed_at.is_some());
+    assert!(AccountAuth::decrypt(&saved, &AuthKey::passphrase("hunter3 hunter3")).is_err());
+    assert!(AccountAuth::decrypt(&saved, &AuthKey::raw([7; 32])).is_err());
+}
+
+#[tokio::test]
+async fn raw_key_encryption_round_trips() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let encrypted = auth.encrypt(&AuthKey::raw([7; 32])).unwrap();
+
+    let decrypted = AccountAuth::decrypt(&encrypted, &AuthKey::raw([7; 32])).unwrap();
+    assert_eq!(decrypted.info(), auth.info());
+    Account::from_auth(decrypted, mock.endpoints())
+        .unwrap()
+        .get_email_phone_info()
+        .await
+        .unwrap();
+    assert!(AccountAuth::decrypt(&encrypted, &AuthKey::raw([8; 32])).is_err());
+}
+
+async fn check_store(store: &dyn AuthStore, auth: &AccountAuth) {
+    assert!(store.load("alice").await.unwrap().is_none());
+    store.save("alice", auth).await.unwrap();
+    store.save("bob", auth).await.unwrap();
+    store.save("alice", auth).await.unwrap();
+    assert_eq!(store.list().await.unwrap(), ["alice", "bob"]);
+    let loaded = store.load("alice").await.unwrap().unwrap();
+    assert_eq!(loaded.info(), auth.info());
+    assert!(store.delete("alice").await.unwrap());
+    assert!(!store.delete("alice").await.unwrap());
+    assert_eq!(store.list().await.unwrap(), ["bob"]);
+}
+
+#[tokio::test]
+async fn auth_stores_load_save_list_delete() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let dir = std::env::temp_dir().join(format!("x-rs-store-{}", mock.addr.port()));
+
+    check_store(&MemoryAuthStore::new(), &auth).await;
+    #[cfg(feature = "sqlite")]
+    check_store(&SqliteAuthStore::in_memory().unwrap(), &auth).await;
+    check_store(&FileAuthStore::new(&dir).unwrap(), &auth).await;
+    let store = FileAuthStore::new(&dir).unwrap();
+    assert!(store.save("../escape", &auth).await.is_err());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.join("bob.json"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let temp_files = std::fs::read_dir(&dir)
+        .unwrap()
+        .filter(|entry| {
+            let name = entry.as_ref().unwrap().file_name();
+            name.to_string_lossy().ends_with(".tmp")
+        })
+        .count();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(temp_files, 0);
+}
+
+#[tokio::test]
+async fn file_store_waits_for_lock_without_blocking_runtime() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let dir = std::env::temp_dir().join(format!("x-rs-lock-wait-{}", mock.addr.port()));
+    let store = FileAuthStore::new(&dir).unwrap();
+    let lock = std::fs::File::create(dir.join(format!(".{}.json.lock", common::USERNAME))).unwrap();
+    lock.lock().unwrap();
+
+    let save = tokio::spawn(async move { store.save(common::USERNAME, &auth).await });
+    // On this single-threaded runtime, a save blocked on the lock would
+    // keep the timer from firing.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!save.is_finished());
+    lock.unlock().unwrap();
+    save.await.unwrap().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlite_store_waits_for_database_without_blocking_runtime() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let path = std::env::temp_dir().join(format!("x-rs-sqlite-wait-{}.db", mock.addr.port()));
+    let store = SqliteAuthStore::open(&path).unwrap();
+    let other = rusqlite::Connection::open(&path).unwrap();
+    other.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+    let save = tokio::spawn(async move { store.compare_and_swap(common::USERNAME, &auth).await });
+    // On this single-threaded runtime, a save waiting on the other
+    // connection would keep the timer from firing.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!save.is_finished());
+    other.execute_batch("COMMIT").unwrap();
+    save.await.unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn refresh_cookies_saves_to_store() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    #[cfg(feature = "sqlite")]
+    let store = Arc::new(
+        SqliteAuthStore::in_memory()
+            .unwrap()
+            .with_key(AuthKey::raw([7; 32])),
+    );
+    #[cfg(not(feature = "sqlite"))]
+    let store = Arc::new(MemoryAuthStore::new());
+    store.save(common::USERNAME, &auth).await.unwrap();
+
+    let mut account = Account::from_store(store.clone(), common::USERNAME, mock.endpoints())
+        .await
+        .unwrap();
+    account.refresh_cookies().await.unwrap();
+
+    let saved = store.load(common::USERNAME).await.unwrap().unwrap();
+    assert!(saved.info().last_refreshed_at.is_some());
+    assert!(Account::from_store(store, "nobody", mock.endpoints())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn concurrent_refresh_reloads_newer_auth() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let path = std::env::temp_dir().join(format!("x-rs-auth-cas-{}.json", mock.addr.port()));
+    std::fs::write(&path, serde_json::to_string(&auth).unwrap()).unwrap();
+    let mut first = Account::from_file(&path, mock.endpoints()).unwrap();
+    let mut second = Account::from_file(&path, mock.endpoints()).unwrap();
+
+    first.refresh_cookies().await.unwrap();
+    // `second` still holds the ct0 `first` rotated away, and must not write
+    // it back over the newer file.
+    second.save().await.unwrap();
+    let saved = Account::from_file(&path, mock.endpoints()).unwrap().auth();
+    assert_eq!(saved.revision(), 1);
+    assert_eq!(second.auth().revision(), 1);
+    second.get_email_phone_info().await.unwrap();
+
+    first.save().await.unwrap();
+    second.refresh_cookies().await.unwrap();
+    assert_eq!(second.auth().revision(), 3);
+    first.refresh_cookies().await.unwrap();
+    first.get_email_phone_info().await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn auth_store_compare_and_swap_rejects_stale_revision() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let stores: Vec<Box<dyn AuthStore>> = vec![
+        Box::new(MemoryAuthStore::new()),
+        #[cfg(feature = "sqlite")]
+        Box::new(SqliteAuthStore::in_memory().unwrap()),
+    ];
+    for store in stores {
+        let saved = store.compare_and_swap("alice", &auth).await.unwrap();
+        assert!(matches!(saved, SaveOutcome::Saved(1)));
+        let stale = store.compare_and_swap("alice", &auth).await.unwrap();
+        let SaveOutcome::Stale(newer) = stale else {
+            panic!("stale revision was saved");
+        };
+        assert_eq!(newer.revision(), 1);
+        let saved = store.compare_and_swap("alice", &newer).await.unwrap();
+        assert!(matches!(saved, SaveOutcome::Saved(2)));
+    }
+}
+
+#[test]
+fn v1_auth_moves_proxy_and_user_agent_into_client_config() {
+    let v1 = json!({
+        "version": 1,
+        "user_id": common::USER_ID,
+        "proxy": "socks5h://127.0.0.1:1080",
+        "user_agent": "Mozilla/5.0 (v1)",
+        "headers": {
+            "user-agent": "Mozilla/5.0 (v1)",
+            "x-twitter-client-language": "en",
+            "x-guest-token": "guest-1",
+        },
+        "cookies": "",
+    });
+    let auth: AccountAuth = serde_json::from_value(v1).unwrap();
+    assert_eq!(auth.version(), AUTH_VERSION);
+    assert_eq!(auth.client().proxy(), Some("socks5h://127.0.0.1:1080"));
+    let profile = auth.client().profile().unwrap();
+    assert_eq!(profile.user_agent(), "Mozilla/5.0 (v1)");
+    assert_eq!(profile.client_language(), "en");
+    assert_eq!(auth.info().user_id.as_deref(), Some(common::USER_ID));
+    let saved = serde_json::to_value(&auth).unwrap();
+    assert!(saved["headers"].get("x-guest-token").is_none());
+}
+
+#[tokio::test]
+async fn login_and_account_share_proxy_and_user_agent() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    // Only reachable through the proxy, which is the mock itself.
+    let endpoints = Endpoints::local("http://x-rs-proxy-test.invalid");
+    let config = ClientConfig::new()
+        .with_proxy(mock.base_url())
+        .with_timeout(Duration::from_secs(10))
+        .with_connect_timeout(Duration::from_secs(5))
+        .with_pool_max_idle_per_host(1);
+    let credentials = LoginCredentials::username(common::USERNAME, common::PASSWORD);
+    let auth = Login::new(credentials, config.clone(), endpoints.clone())
+        .unwrap()
+        .login()
+        .await
+        .unwrap();
+    let user_agent = auth.client().profile().unwrap().user_agent().to_string();
+    assert_eq!(auth.client().proxy(), Some(mock.base_url().as_str()));
+    assert_eq!(auth.client().timeout(), Some(Duration::from_secs(10)));
+
+    let auth: AccountAuth = serde_json::from_str(&serde_json::to_string(&auth).unwrap()).unwrap();
+    let mut account = Account::from_auth(auth, endpoints).unwrap();
+    account.refresh_cookies().await.unwrap();
+    account.get_email_phone_info().await.unwrap();
+
+    let state = mock.state();
+    assert_eq!(state.proxied_requests, state.user_agents.len());
+    assert!(state.user_agents.iter().all(|seen| *seen == user_agent));
+}
+
+#[tokio::test]
+async fn configured_profile_is_used_and_persisted() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let profile =
+        ClientProfile::for_user_agent("Mozilla/5.0 (pinned)").with_language("de-DE,de;q=0.9", "de");
+    let config = ClientConfig::new().with_profile(profile.clone());
+    let credentials = LoginCredentials::username(common::USERNAME, common::PASSWORD);
+    let auth = Login::new(credentials, config, mock.endpoints())
+        .unwrap()
+        .login()
+        .await
+        .unwrap();
+    assert_eq!(auth.client().profile(), Some(&profile));
+    let saved = serde_json::to_value(&auth).unwrap();
+    assert_eq!(saved["headers"]["x-twitter-client-language"], json!("de"));
+    assert!(saved["headers"].get("x-guest-token").is_none());
+    assert!(saved["headers"].get("content-type").is_none());
+
+    let account = Account::from_auth(auth, mock.endpoints()).unwrap();
+    account.get_email_phone_info().await.unwrap();
+    assert_eq!(account.client_config().profile(), Some(&profile));
+    let state = mock.state();
+    assert!(state
+        .user_agents
+        .iter()
+        .all(|seen| seen == "Mozilla/5.0 (pinned)"));
+}
+
+#[test]
+fn generated_profiles_are_consistent() {
+    for _ in 0..20 {
+        let profile = ClientProfile::generate();
+        let chrome = profile.user_agent().contains("Chrome/");
+        if let Some(sec_ch_ua) = profile.sec_ch_ua() {
+            assert!(chrome);
+            assert!(sec_ch_ua.contains("Google Chrome"));
+            assert!(profile.sec_ch_ua_platform().is_some());
+        }
+        assert_eq!(profile.client_language(), "en");
+        assert!(profile.bearer_token().starts_with("Bearer "));
+    }
+    let chrome = ClientProfile::for_user_agent(
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+         Chrome/124.0.0.0 Safari/537.36",
+    );
+    assert_eq!(chrome.sec_ch_ua_platform(), Some("\"Windows\""));
+    assert_eq!(chrome.sec_ch_ua_mobile(), Some("?0"));
+    assert!(chrome.sec_ch_ua().unwrap().contains("v=\"124\""));
+}
+
+#[tokio::test]
+async fn csrf_token_follows_ct0_rotation_and_is_saved() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let store = Arc::new(MemoryAuthStore::new());
+    store
+        .save(common::USERNAME, &mock.login_auth().await)
+        .await
+        .unwrap();
+    let account = Account::from_store(store.clone(), common::USERNAME, mock.endpoints())
+        .await
+        .unwrap();
+    mock.state().rotate_ct0_always = true;
+
+    for _ in 0..3 {
+        account.get_email_phone_info().await.unwrap();
+    }
+    account.get_all_oauth_applications().await.unwrap();
+
+    let ct0 = mock.state().ct0.clone().unwrap();
+    let saved = store.load(common::USERNAME).await.unwrap().unwrap();
+    assert_eq!(saved.revision(), 4);
+    let saved = serde_json::to_value(&saved).unwrap();
+    assert_eq!(saved["headers"]["x-csrf-token"], json!(ct0));
+    let reloaded = Account::from_store(store, common::USERNAME, mock.endpoints())
+        .await
+        .unwrap();
+    mock.state().rotate_ct0_always = false;
+    reloaded.get_email_phone_info().await.unwrap();
+}
+
+/// Makes X forget the session, with a password login ready for the next
+/// attempt.
+fn revoke_session(mock: &MockX) {
+    let mut state = mock.state();
+    state.auth_token = Some("revoked".to_string());
+    state.script = password_script().into();
+}
+
+#[tokio::test]
+async fn dead_session_logs_in_again_and_retries() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let store = Arc::new(MemoryAuthStore::new());
+    store
+        .save(common::USERNAME, &mock.login_auth().await)
+        .await
+        .unwrap();
+    let account = Account::from_store(store.clone(), common::USERNAME, mock.endpoints())
+        .await
+        .unwrap();
+    revoke_session(&mock);
+    let error = account.get_email_phone_info().await.unwrap_err();
+    let error = error.downcast_ref::<XApiError>().unwrap();
+    assert_eq!(error.code, Some(XErrorCode::BadAuthentication));
+
+    let credentials =
+        LoginCredentials::username(common::USERNAME, common::PASSWORD).with_email(common::EMAIL);
+    let account = account.with_reauth(ReauthPolicy::credentials(credentials));
+    account.get_email_phone_info().await.unwrap();
+
+    // The login went out as the same browser the session was using.
+    let user_agents = mock.state().user_agents.clone();
+    assert!(user_agents.windows(2).all(|pair| pair[0] == pair[1]));
+    let reloaded = Account::from_store(store, common::USERNAME, mock.endpoints())
+        .await
+        .unwrap();
+    reloaded.get_email_phone_info().await.unwrap();
+
+    // A 403 that names no error code is a dead session too.
+    revoke_session(&mock);
+    mock.state().bare_forbidden = true;
+    account.get_email_phone_info().await.unwrap();
+}
+
+#[tokio::test]
+async fn reauth_refuses_another_users_session() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let store = Arc::new(MemoryAuthStore::new());
+    store
+        .save(common::USERNAME, &mock.login_auth().await)
+        .await
+        .unwrap();
+    let credentials = LoginCredentials::email(common::EMAIL, common::PASSWORD);
+    let account = Account::from_store(store.clone(), common::USERNAME, mock.endpoints())
+        .await
+        .unwrap()
+        .with_reauth(ReauthPolicy::credentials(credentials));
+    let dead = account.auth_cookie_string();
+    revoke_session(&mock);
+    mock.state().other_user_id = Some("42".to_string());
+
+    let error = account.get_email_phone_info().await.unwrap_err();
+    assert!(format!("{error:#}").contains("instead of"), "{error:#}");
+    assert_eq!(account.auth_cookie_string(), dead);
+    assert_eq!(account.info().user_id.as_deref(), Some(common::USER_ID));
+    let saved = store.load(common::USERNAME).await.unwrap().unwrap();
+    assert_eq!(saved.info().user_id.as_deref(), Some(common::USER_ID));
+}
+
+#[tokio::test]
+async fn reauth_picks_up_the_new_screen_name() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let credentials = LoginCredentials::email(common::EMAIL, common::PASSWORD);
+    let account = mock
+        .account()
+        .await
+        .with_reauth(ReauthPolicy::credentials(credentials));
+    assert_eq!(account.me().await.unwrap().screen_name, common::USERNAME);
+
+    revoke_session(&mock);
+    mock.state().verified_screen_name = Some("renamed".to_string());
+    account.get_email_phone_info().await.unwrap();
+    assert_eq!(account.info().screen_name.as_deref(), Some("renamed"));
+    assert_eq!(account.me().await.unwrap().screen_name, "renamed");
+}
+
+struct CountingProvider(Arc<AtomicUsize>);
+
+#[async_trait]
+impl CredentialsProvider for CountingProvider {
+    async fn credentials(&self) -> eyre::Result<LoginCredentials> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(
+            LoginCredentials::username(common::USERNAME, common::PASSWORD)
+                .with_email(common::EMAIL),
+        )
+    }
+}
+
+#[tokio::test]
+async fn concurrent_auth_failures_log_in_once() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let logins = Arc::new(AtomicUsize::new(0));
+    let account = mock
+        .account()
+        .await
+        .with_reauth(ReauthPolicy::provider(CountingProvider(logins.clone())));
+    revoke_session(&mock);
+
+    let (first, second) = tokio::join!(
+        account.get_email_phone_info(),
+        account.get_all_oauth_applications()
+    );
+    first.unwrap();
+    second.unwrap();
+    assert_eq!(logins.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn verify_credentials_returns_and_caches_the_user() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let account = mock.account().await;
+
+    let me = account.me().await.unwrap();
+    assert_eq!(
+        me,
+        UserProfile {
+            user_id: common::USER_ID.to_string(),
+            screen_name: common::USERNAME.to_string(),
+            name: "Mock User".to_string(),
+            created_at: 1_638_504_306,
+            followers: 42,
+            protected: false,
+            verified: false,
+            suspended: false,
+            locked: false,
+        }
+    );
+
+    // Cached, so the revoked session goes unnoticed until asked again.
+    mock.state().auth_token = Some("revoked".to_string());
+    assert_eq!(account.me().await.unwrap(), me);
+    let error = account.verify_credentials().await.unwrap_err();
+    let error = error.downcast_ref::<XApiError>().unwrap();
+    assert!(error.is_auth_failure());
+}
+
+#[tokio::test]
+async fn phones_are_added_with_sms_code_and_removed() {
+    let account = MockAccount {
+        sms_code: Some("424242".to_string()),
+        phone_numbers: vec![json!({"id": "7", "phone_number": "+4********89"})],
+        ..Default::default()
+    };
+    let mock = MockX::start(account, password_script()).await;
+    let account = mock.account().await;
+    let (codes, provider) = ChannelCodeProvider::new();
+
+    codes.send("000000".to_string()).unwrap();
+    assert!(account.add_phone(common::PHONE, &provider).await.is_err());
+    codes.send("424242".to_string()).unwrap();
+    let phone = account.add_phone(common::PHONE, &provider).await.unwrap();
+    assert!(phone.verified && phone.is_masked());
+    assert!(phone.matches(common::PHONE) && !phone.matches("+15555550124"));
+    assert_eq!(account.list_phones().await.unwrap().len(), 2);
+
+    account.remove_phone(&phone).await.unwrap();
+    assert_eq!(account.list_phones().await.unwrap().len(), 1);
+    account.remove_all_phones().await.unwrap();
+    assert!(account.list_phones().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn change_email_confirms_code_and_drops_old_address() {
+    let account = MockAccount {
+        email_code: Some("135790".to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, password_script()).await;
+    let account = mock.account().await;
+    let (codes, provider) = ChannelCodeProvider::new();
+
+    assert!(account
+        .change_email("new@example.com", "wrong password", &provider)
+        .await
+        .is_err());
+    codes.send("135790".to_string()).unwrap();
+    account
+        .change_email("new@example.com", common::PASSWORD, &provider)
+        .await
+        .unwrap();
+    let answered = mock.answered();
+    assert_eq!(
+        answered[answered.len() - 3..],
+        ["EnterPassword", "EmailAssocEnterEmail", "EmailVerification"]
+    );
+    let emails = account.get_email_phone_info().await.unwrap().emails;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].email, "new@example.com");
+
+    mock.state().keeps_old_email = true;
+    codes.send("135790".to_string()).unwrap();
+    let error = account
+        .change_email("newer@example.com", common::PASSWORD, &provider)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("new@example.com"), "{error}");
+}
@@ -0,0 +1,886 @@
+//! Offline stand-in for the parts of `api.x.com` and `x.com` the crate uses.
+//!
+//! The server hands out flow tokens for `/1.1/onboarding/task.json`, walks a
+//! scripted list of subtasks, checks that every request answers the subtask
+//! it was last given, and sets `ct0`/`auth_token` cookies when the login
+//! succeeds. Account endpoints require a matching `auth_token` cookie and
+//! `x-csrf-token` header, like the real site.
+#![allow(dead_code)]
+
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::{json, Value};
+use totp_rs::{Algorithm, Secret, TOTP};
+use x_rs::account::{
+    login::Login, Account, AccountAuth, ClientConfig, Endpoints, LoginCredentials,
+};
+
+pub const USERNAME: &str = "mock_user";
+pub const PASSWORD: &str = "correct horse battery staple";
+pub const PHONE: &str = "+15555550123";
+pub const USER_ID: &str = "1467726470533754880";
+pub const EMAIL: &str = "mock_user@example.com";
+pub const TOTP_SECRET: &str = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+
+#[derive(Debug, Clone)]
+pub struct MockAccount {
+    pub username: String,
+    pub password: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub totp_secret: Option<String>,
+    pub totp_algorithm: Algorithm,
+    pub totp_digits: usize,
+    pub totp_period: u64,
+    pub sms_code: Option<String>,
+    /// Unused backup codes; each one is removed once accepted.
+    pub backup_codes: Vec<String>,
+    /// When set, `LoginAcid` must be answered with this code instead of the
+    /// email address. Also the code the `add_email` flow expects.
+    pub email_code: Option<String>,
+    /// The Arkose token the captcha subtask must be answered with.
+    pub captcha_token: Option<String>,
+    pub emails: Vec<Value>,
+    pub phone_numbers: Vec<Value>,
+    pub applications: Vec<Value>,
+}
+
+impl MockAccount {
+    fn identifies(&self, identifier: &str) -> bool {
+        identifier == self.username
+            || identifier == self.email
+            || Some(identifier) == self.phone.as_deref()
+    }
+}
+
+impl Default for MockAccount {
+    fn default() -> Self {
+        Self {
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+            email: EMAIL.to_string(),
+            phone: None,
+            totp_secret: None,
+            totp_algorithm: Algorithm::SHA1,
+            totp_digits: 6,
+            totp_period: 30,
+            sms_code: None,
+            backup_codes: vec![],
+            email_code: None,
+            captcha_token: None,
+            emails: vec![json!({"email": EMAIL, "email_verified": true})],
+            phone_numbers: vec![],
+            applications: vec![
+                json!({"token": "token-1", "app_id": "1001"}),
+                json!({"token": "token-2", "app_id": "1002"}),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub account: MockAccount,
+    /// Subtasks handed out, in order, after the JS instrumentation subtask.
+    pub script: VecDeque<Value>,
+    /// Every `subtask_inputs` entry the client submitted, in order.
+    pub inputs: Vec<Value>,
+    pub guest_token: Option<String>,
+    pub flow_token: Option<String>,
+    pub pending_subtask: Option<String>,
+    pub auth_token: Option<String>,
+    pub ct0: Option<String>,
+    pub revoked: Vec<String>,
+    /// Returned instead of the next scripted subtask, as `(status, code,
+    /// message)`.
+    pub task_error: Option<(StatusCode, u32, String)>,
+    /// Seconds the server's clock, and its `Date` header, run ahead of the
+    /// machine's.
+    pub clock_offset: i64,
+    pub used_totp_codes: Vec<String>,
+    /// The `User-Agent` of every request, in order.
+    pub user_agents: Vec<String>,
+    /// Requests that came through a forward proxy, i.e. with an absolute
+    /// URI.
+    pub proxied_requests: usize,
+    /// Like X under load, rotate `ct0` on every successful response.
+    pub rotate_ct0_always: bool,
+    /// `verify_credentials` answers as this user instead, like a login
+    /// that ended up in another account.
+    pub verified_screen_name: Option<String>,
+    /// Logins, and `verify_credentials`, end up in this user instead of
+    /// [`USER_ID`], like credentials that belong to another account.
+    pub other_user_id: Option<String>,
+    /// The number `device/register` last texted a code to.
+    pub pending_phone: Option<String>,
+    /// The address the `add_email` flow last mailed a code to.
+    pub pending_email: Option<String>,
+    /// Like a half-finished change on X's side, leave the old address
+    /// attached after verifying the new one.
+    pub keeps_old_email: bool,
+    /// Turn dead sessions away with a bare 403, as X sometimes does,
+    /// instead of error code 32.
+    pub bare_forbidden: bool,
+    /// The flow the last `task.json` start began.
+    flow_name: Option<String>,
+    sequence: u32,
+}
+
+impl MockState {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.sequence += 1;
+        format!("{prefix}-{}", self.sequence)
+    }
+
+    fn now(&self) -> SystemTime {
+        let offset = Duration::from_secs(self.clock_offset.unsigned_abs());
+        if self.clock_offset >= 0 {
+            SystemTime::now() + offset
+        } else {
+            SystemTime::now() - offset
+        }
+    }
+
+    fn rotate_ct0(&mut self) -> String {
+        let ct0 = self.next_id("ct0");
+        self.ct0 = Some(ct0.clone());
+        ct0
+    }
+}
+
+pub struct MockX {
+    pub addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockX {
+    pub async fn start(account: MockAccount, script: Vec<Value>) -> Self {
+        let state = Arc::new(Mutex::new(MockState {
+            account,
+            script: script.into(),
+            ..Default::default()
+        }));
+        let app = Router::new()
+            .route("/1.1/guest/activate.json", post(guest_activate))
+            .route("/1.1/onboarding/task.json", post(task))
+            .route(
+                "/i/api/1.1/users/email_phone_info.json",
+                get(email_phone_info),
+            )
+            .route(
+                "/1.1/account/verify_credentials.json",
+                get(verify_credentials),
+            )
+            .route("/1.1/oauth/list.json", get(oauth_list))
+            .route("/i/api/1.1/oauth/revoke.json", post(oauth_revoke))
+            .route(
+                "/i/api/i/account/change_password.json",
+                post(change_password),
+            )
+            .route("/i/api/2/notifications/all.json", get(notifications))
+            .route("/i/api/1.1/device/register.json", post(register_phone))
+            .route(
+                "/i/api/1.1/device/register_complete.json",
+                post(register_phone_complete),
+            )
+            .route("/i/api/1.1/device/unregister.json", post(unregister_phone))
+            .layer(middleware::map_response_with_state(
+                state.clone(),
+                server_date,
+            ))
+            .layer(middleware::map_request_with_state(
+                state.clone(),
+                record_request,
+            ))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Self { addr, state }
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::local(self.base_url())
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// The subtask ids the client answered, in order.
+    pub fn answered(&self) -> Vec<String> {
+        self.state()
+            .inputs
+            .iter()
+            .map(|input| input["subtask_id"].as_str().unwrap_or("").to_string())
+            .collect()
+    }
+
+    pub fn login(&self, totp_secret: Option<&str>) -> Login {
+        let mut credentials = LoginCredentials::username(USERNAME, PASSWORD).with_email(EMAIL);
+        if let Some(totp_secret) = totp_secret {
+            credentials = credentials.with_totp_secret(totp_secret).unwrap();
+        }
+        self.login_with(credentials)
+    }
+
+    pub fn login_with(&self, credentials: LoginCredentials) -> Login {
+        Login::new(credentials, ClientConfig::default(), self.endpoints()).unwrap()
+    }
+
+    pub async fn login_auth(&self) -> AccountAuth {
+        let totp_secret = self.state().account.totp_secret.clone();
+        self.login(totp_secret.as_deref()).login().await.unwrap()
+    }
+
+    pub async fn account(&self) -> Account {
+        Account::from_auth(self.login_auth().await, self.endpoints()).unwrap()
+    }
+}
+
+pub fn subtask(id: &str) -> Value {
+    json!({ "subtask_id": id })
+}
+
+/// The sequence X serves for a plain username and password login.
+pub fn password_script() -> Vec<Value> {
+    vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        subtask("LoginSuccessSubtask"),
+    ]
+}
+
+pub fn email_code_subtask() -> Value {
+    json!({
+        "subtask_id": "LoginAcid",
+        "enter_text": {
+            "primary_text": {"text": "Check your email"},
+            "hint_text": "Confirmation code",
+        },
+    })
+}
+
+/// A `LoginTwoFactorAuthChallenge` for the method named in `prompt`, with
+/// the link to pick another method.
+pub fn two_factor_subtask(prompt: &str) -> Value {
+    json!({
+        "subtask_id": "LoginTwoFactorAuthChallenge",
+        "enter_text": {
+            "primary_text": {"text": "Enter your verification code"},
+            "secondary_text": {"text": prompt},
+            "hint_text": "Enter code",
+            "skip_link": {
+                "link_id": "choose_2fa_method_link",
+                "subtask_id": "LoginTwoFactorAuthChooseMethod",
+            },
+        },
+    })
+}
+
+pub fn choose_method_subtask() -> Value {
+    json!({
+        "subtask_id": "LoginTwoFactorAuthChooseMethod",
+        "choice_selection": {
+            "choices": [
+                {"id": "0", "text": {"text": "Authentication app"}},
+                {"id": "1", "text": {"text": "Text message"}},
+                {"id": "2", "text": {"text": "Backup code"}},
+            ],
+        },
+    })
+}
+
+pub fn arkose_subtask() -> Value {
+    json!({
+        "subtask_id": "ArkoseLogin",
+        "web_modal": {
+            "url": "https://x.com/i/flow/arkose?public_key=2F4F0B28-BC94-4271-8AD7-A51662E3C91C&data=blob-123",
+        },
+    })
+}
+
+pub fn totp(account: &MockAccount) -> Option<TOTP> {
+    let secret = account.totp_secret.as_ref()?;
+    Some(TOTP::new_unchecked(
+        account.totp_algorithm,
+        account.totp_digits,
+        1,
+        account.totp_period,
+        Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+    ))
+}
+
+type Shared = State<Arc<Mutex<MockState>>>;
+
+async fn record_request(State(state): Shared, request: Request) -> Request {
+    let mut state = state.lock().unwrap();
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    state.user_agents.push(user_agent.to_string());
+    if request.uri().scheme().is_some() {
+        state.proxied_requests += 1;
+    }
+    request
+}
+
+async fn server_date(State(state): Shared, mut response: Response) -> Response {
+    let mut state = state.lock().unwrap();
+    let now = state.now();
+    if state.rotate_ct0_always && response.status().is_success() {
+        let ct0 = state.rotate_ct0();
+        set_cookie(response.headers_mut(), "ct0", &ct0);
+    }
+    response
+        .headers_mut()
+        .insert(header::DATE, httpdate::fmt_http_date(now).parse().unwrap());
+    response
+}
+
+fn x_error(status: StatusCode, code: u32, message: &str) -> Response {
+    (
+        status,
+        Json(json!({"errors": [{"code": code, "message": message}]})),
+    )
+        .into_response()
+}
+
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+fn set_cookie(headers: &mut HeaderMap, name: &str, value: &str) {
+    headers.append(
+        header::SET_COOKIE,
+        format!("{name}={value}; Path=/; Max-Age=31536000")
+            .parse()
+            .unwrap(),
+    );
+}
+
+fn check_bearer(headers: &HeaderMap) -> Option<Response> {
+    match headers.get(header::AUTHORIZATION) {
+        Some(value) if value.as_bytes().starts_with(b"Bearer ") => None,
+        _ => Some(x_error(
+            StatusCode::UNAUTHORIZED,
+            215,
+            "Bad Authentication data.",
+        )),
+    }
+}
+
+fn check_session(state: &mut MockState, headers: &HeaderMap) -> Option<Response> {
+    if let Some(response) = check_bearer(headers) {
+        return Some(response);
+    }
+    if state.auth_token.is_none() || cookie(headers, "auth_token") != state.auth_token {
+        if state.bare_forbidden {
+            return Some(StatusCode::FORBIDDEN.into_response());
+        }
+        return Some(x_error(
+            StatusCode::UNAUTHORIZED,
+            32,
+            "Could not authenticate you.",
+        ));
+    }
+    let csrf = headers
+        .get("x-csrf-token")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    if csrf.is_none() || csrf != cookie(headers, "ct0") || csrf != state.ct0 {
+        // Like X, hand out a fresh ct0 along with the rejection.
+        let mut response = x_error(
+            StatusCode::FORBIDDEN,
+            353,
+            "This request requires a matching csrf cookie and header.",
+        );
+        let ct0 = state.rotate_ct0();
+        set_cookie(response.headers_mut(), "ct0", &ct0);
+        return Some(response);
+    }
+    None
+}
+
+async fn guest_activate(State(state): Shared, headers: HeaderMap) -> Response {
+    if let Some(response) = check_bearer(&headers) {
+        return response;
+    }
+    let mut state = state.lock().unwrap();
+    let guest_token = state.next_id("guest");
+    state.guest_token = Some(guest_token.clone());
+    Json(json!({ "guest_token": guest_token })).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct TaskQuery {
+    flow_name: Option<String>,
+}
+
+async fn task(
+    State(state): Shared,
+    Query(query): Query<TaskQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if let Some(response) = check_bearer(&headers) {
+        return response;
+    }
+    let mut state = state.lock().unwrap();
+    if let Some(flow_name) = &query.flow_name {
+        state.flow_name = Some(flow_name.clone());
+    }
+    if state.flow_name.as_deref() == Some("add_email") {
+        return add_email_task(&mut state, &headers, query.flow_name.is_some(), &body);
+    }
+    let guest_token = headers
+        .get("x-guest-token")
+        .and_then(|value| value.to_str().ok());
+    if guest_token.is_none() || guest_token != state.guest_token.as_deref() {
+        return x_error(StatusCode::FORBIDDEN, 239, "Bad guest token.");
+    }
+    let Ok(body) = serde_json::from_str::<Value>(&body) else {
+        return x_error(StatusCode::BAD_REQUEST, 214, "Malformed request body.");
+    };
+
+    if query.flow_name.is_some() {
+        let flow_token = state.next_id("flow");
+        state.flow_token = Some(flow_token.clone());
+        state.pending_subtask = Some("LoginJsInstrumentationSubtask".to_string());
+        return Json(json!({
+            "flow_token": flow_token,
+            "status": "success",
+            "subtasks": [subtask("LoginJsInstrumentationSubtask")],
+        }))
+        .into_response();
+    }
+
+    if let Some((status, code, message)) = state.task_error.take() {
+        let mut response = x_error(status, code, &message);
+        response
+            .headers_mut()
+            .insert("x-rate-limit-reset", "1700000000".parse().unwrap());
+        return response;
+    }
+    if body["flow_token"].as_str() != state.flow_token.as_deref() {
+        return x_error(StatusCode::BAD_REQUEST, 366, "flow_token is invalid.");
+    }
+    let pending = state.pending_subtask.clone().unwrap_or_default();
+    let input = body["subtask_inputs"]
+        .as_array()
+        .and_then(|inputs| inputs.first())
+        .cloned();
+    let mut response_headers = HeaderMap::new();
+    match input {
+        Some(input) => {
+            if input["subtask_id"].as_str() != Some(pending.as_str()) {
+                return x_error(
+                    StatusCode::BAD_REQUEST,
+                    366,
+                    &format!("Expected an answer to {pending}."),
+                );
+            }
+            if let Some(response) = check_input(&mut state, &input) {
+                return response;
+            }
+            state.inputs.push(input);
+        }
+        None if pending == "LoginSuccessSubtask" => {
+            state.inputs.push(subtask("LoginSuccessSubtask"));
+            let ct0 = state.rotate_ct0();
+            set_cookie(&mut response_headers, "ct0", &ct0);
+            state.pending_subtask = None;
+            let flow_token = state.next_id("flow");
+            state.flow_token = Some(flow_token.clone());
+            return (
+                response_headers,
+                Json(json!({ "flow_token": flow_token, "status": "success", "subtasks": [] })),
+            )
+                .into_response();
+        }
+        None => {
+            return x_error(
+                StatusCode::BAD_REQUEST,
+                366,
+                &format!("Expected an answer to {pending}."),
+            );
+        }
+    }
+
+    let next = state.script.pop_front();
+    let subtasks = match next {
+        Some(next) => {
+            let id = next["subtask_id"].as_str().unwrap_or_default().to_string();
+            if id == "LoginSuccessSubtask" {
+                let auth_token = state.next_id("auth");
+                state.auth_token = Some(auth_token.clone());
+                let ct0 = state.rotate_ct0();
+                set_cookie(&mut response_headers, "auth_token", &auth_token);
+                set_cookie(&mut response_headers, "ct0", &ct0);
+                let user_id = state.other_user_id.as_deref().unwrap_or(USER_ID);
+                set_cookie(&mut response_headers, "twid", &format!("u%3D{user_id}"));
+            }
+            state.pending_subtask = Some(id);
+            vec![next]
+        }
+        None => {
+            state.pending_subtask = None;
+            vec![]
+        }
+    };
+    let flow_token = state.next_id("flow");
+    state.flow_token = Some(flow_token.clone());
+    (
+        response_headers,
+        Json(json!({ "flow_token": flow_token, "status": "success", "subtasks": subtasks })),
+    )
+        .into_response()
+}
+
+fn check_input(state: &mut MockState, input: &Value) -> Option<Response> {
+    let now = state.now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let account = &state.account;
+    let text = input["enter_text"]["text"].as_str();
+    let rejection =
+        match input["subtask_id"].as_str().unwrap_or_default() {
+            "LoginEnterUserIdentifierSSO" => {
+                let identifier = input["settings_list"]["setting_responses"][0]["response_data"]
+                    ["text_data"]["result"]
+                    .as_str();
+                (!identifier.is_some_and(|identifier| account.identifies(identifier)))
+                    .then_some("Sorry, we could not find your account.")
+            }
+            "LoginEnterPassword" => (input["enter_password"]["password"].as_str()
+                != Some(account.password.as_str()))
+            .then_some("Incorrect. Please try again."),
+            "LoginTwoFactorAuthChallenge" => match text {
+                None if input["enter_text"]["link"] == "choose_2fa_method_link" => None,
+                Some(code) if state.used_totp_codes.iter().any(|used| used == code) => {
+                    Some("This code has already been used.")
+                }
+                Some(code) if totp(account).is_some_and(|totp| totp.check(code, now)) => {
+                    state.used_totp_codes.push(code.to_string());
+                    None
+                }
+                Some(code) if account.sms_code.as_deref() == Some(code) => None,
+                Some(code) if account.backup_codes.iter().any(|backup| backup == code) => {
+                    state.account.backup_codes.retain(|backup| backup != code);
+                    None
+                }
+                _ => Some("Your code was incorrect."),
+            },
+            "LoginAcid" if account.email_code.is_some() => (text != account.email_code.as_deref())
+                .then_some("The code you entered is incorrect."),
+            "ArkoseLogin" | "LoginArkoseChallenge" => {
+                let expected = account.captcha_token.as_ref().map(|token| {
+                    format!("twitter://onboarding/web_modal/next_link?access_token={token}")
+                });
+                (input["web_modal"]["completion_deeplink"].as_str() != expected.as_deref())
+                    .then_some("Captcha verification failed.")
+            }
+            "LoginEnterAlternateIdentifierSubtask" => (!text
+                .is_some_and(|text| account.identifies(text)))
+            .then_some("Please verify your identity."),
+            "LoginAcid" => (text != Some(account.email.as_str()))
+                .then_some("Please verify your email address."),
+            _ => None,
+        };
+    rejection.map(|message| x_error(StatusCode::BAD_REQUEST, 399, message))
+}
+
+async fn email_phone_info(State(state): Shared, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    Json(json!({
+        "emails": state.account.emails,
+        "phone_numbers": state.account.phone_numbers,
+    }))
+    .into_response()
+}
+
+async fn verify_credentials(State(state): Shared, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    Json(json!({
+        "id_str": state.other_user_id.as_deref().unwrap_or(USER_ID),
+        "screen_name": state
+            .verified_screen_name
+            .as_ref()
+            .unwrap_or(&state.account.username),
+        "name": "Mock User",
+        "created_at": "Fri Dec 03 04:05:06 +0000 2021",
+        "followers_count": 42,
+        "protected": false,
+        "verified": false,
+        "suspended": false,
+        "needs_phone_verification": false,
+    }))
+    .into_response()
+}
+
+async fn oauth_list(State(state): Shared, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    if state.account.applications.is_empty() {
+        return Json(json!({})).into_response();
+    }
+    Json(json!({ "applications": state.account.applications })).into_response()
+}
+
+async fn oauth_revoke(
+    State(state): Shared,
+    headers: HeaderMap,
+    axum::Form(form): axum::Form<std::collections::HashMap<String, String>>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    let Some(token) = form.get("token") else {
+        return x_error(StatusCode::BAD_REQUEST, 38, "token parameter is missing.");
+    };
+    let before = state.account.applications.len();
+    state
+        .account
+        .applications
+        .retain(|app| app["token"].as_str() != Some(token.as_str()));
+    if state.account.applications.len() == before {
+        return x_error(
+            StatusCode::NOT_FOUND,
+            34,
+            "Sorry, that page does not exist.",
+        );
+    }
+    state.revoked.push(token.clone());
+    Json(json!({})).into_response()
+}
+
+async fn change_password(
+    State(state): Shared,
+    headers: HeaderMap,
+    axum::Form(form): axum::Form<std::collections::HashMap<String, String>>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    if form.get("current_password") != Some(&state.account.password) {
+        return x_error(
+            StatusCode::BAD_REQUEST,
+            114,
+            "The password you entered was incorrect.",
+        );
+    }
+    let (Some(password), Some(confirmation)) =
+        (form.get("password"), form.get("password_confirmation"))
+    else {
+        return x_error(
+            StatusCode::BAD_REQUEST,
+            38,
+            "password parameter is missing.",
+        );
+    };
+    if password != confirmation {
+        return x_error(StatusCode::BAD_REQUEST, 114, "Passwords do not match.");
+    }
+    state.account.password = password.clone();
+    Json(json!({ "status": "ok" })).into_response()
+}
+
+async fn notifications(State(state): Shared, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    let ct0 = state.rotate_ct0();
+    let mut response_headers = HeaderMap::new();
+    set_cookie(&mut response_headers, "ct0", &ct0);
+    (response_headers, Json(json!({ "globalObjects": {} }))).into_response()
+}
+
+type Form = axum::Form<std::collections::HashMap<String, String>>;
+
+async fn register_phone(
+    State(state): Shared,
+    headers: HeaderMap,
+    axum::Form(form): Form,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    let Some(phone_number) = form.get("phone_number") else {
+        return x_error(
+            StatusCode::BAD_REQUEST,
+            38,
+            "phone_number parameter is missing.",
+        );
+    };
+    state.pending_phone = Some(phone_number.clone());
+    Json(json!({ "status": "ok" })).into_response()
+}
+
+async fn register_phone_complete(
+    State(state): Shared,
+    headers: HeaderMap,
+    axum::Form(form): Form,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    if state.pending_phone.is_none() || form.get("phone_number") != state.pending_phone.as_ref() {
+        return x_error(StatusCode::BAD_REQUEST, 285, "No verification pending.");
+    }
+    if form.get("numeric_pin") != state.account.sms_code.as_ref() {
+        return x_error(StatusCode::BAD_REQUEST, 236, "Invalid PIN.");
+    }
+    let phone_number = state.pending_phone.take().unwrap();
+    let id = state.next_id("phone");
+    // Like X, list all but the country code and last two digits masked.
+    let (head, tail) = (&phone_number[..2], &phone_number[phone_number.len() - 2..]);
+    let masked = format!("{head}{}{tail}", "*".repeat(phone_number.len() - 4));
+    state.account.phone_numbers.push(json!({
+        "id_str": id,
+        "phone_number": masked,
+        "phone_number_verified": true,
+    }));
+    Json(json!({ "status": "ok", "id_str": id })).into_response()
+}
+
+async fn unregister_phone(
+    State(state): Shared,
+    headers: HeaderMap,
+    axum::Form(form): Form,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = check_session(&mut state, &headers) {
+        return response;
+    }
+    let before = state.account.phone_numbers.len();
+    state.account.phone_numbers.retain(|phone| {
+        let matches = |key: &str, form_key: &str| {
+            phone[key]
+                .as_str()
+                .is_some_and(|v| form.get(form_key).map(String::as_str) == Some(v))
+        };
+        !(matches("id", "id") || matches("id_str", "id") || matches("phone_number", "phone_number"))
+    });
+    if state.account.phone_numbers.len() == before {
+        return x_error(
+            StatusCode::NOT_FOUND,
+            34,
+            "Sorry, that page does not exist.",
+        );
+    }
+    Json(json!({ "status": "ok" })).into_response()
+}
+
+/// X's settings flow for a new address: the password, the address, then
+/// the code mailed to it. Runs on the session rather than a guest token.
+fn add_email_task(state: &mut MockState, headers: &HeaderMap, start: bool, body: &str) -> Response {
+    if let Some(response) = check_session(state, headers) {
+        return response;
+    }
+    let Ok(body) = serde_json::from_str::<Value>(body) else {
+        return x_error(StatusCode::BAD_REQUEST, 214, "Malformed request body.");
+    };
+    let next = if start {
+        Some("EnterPassword")
+    } else {
+        if body["flow_token"].as_str() != state.flow_token.as_deref() {
+            return x_error(StatusCode::BAD_REQUEST, 366, "flow_token is invalid.");
+        }
+        let pending = state.pending_subtask.clone().unwrap_or_default();
+        let input = body["subtask_inputs"][0].clone();
+        if input["subtask_id"].as_str() != Some(pending.as_str()) {
+            return x_error(
+                StatusCode::BAD_REQUEST,
+                366,
+                &format!("Expected an answer to {pending}."),
+            );
+        }
+        let next = match pending.as_str() {
+            "EnterPassword" => {
+                if input["enter_password"]["password"].as_str()
+                    != Some(state.account.password.as_str())
+                {
+                    return x_error(
+                        StatusCode::BAD_REQUEST,
+                        114,
+                        "The password you entered was incorrect.",
+                    );
+                }
+                Some("EmailAssocEnterEmail")
+            }
+            "EmailAssocEnterEmail" => {
+                let Some(email) = input["enter_text"]["text"].as_str() else {
+                    return x_error(StatusCode::BAD_REQUEST, 38, "email parameter is missing.");
+                };
+                state.pending_email = Some(email.to_string());
+                Some("EmailVerification")
+            }
+            _ => {
+                let verification = &input["email_verification"];
+                if verification["email"].as_str() != state.pending_email.as_deref() {
+                    return x_error(StatusCode::BAD_REQUEST, 285, "No verification pending.");
+                }
+                if verification["code"].as_str() != state.account.email_code.as_deref() {
+                    return x_error(StatusCode::BAD_REQUEST, 236, "Invalid code.");
+                }
+                let email = state.pending_email.take().unwrap();
+                let new_email = json!({ "email": email, "email_verified": true, "primary": true });
+                if state.keeps_old_email {
+                    state.account.emails.push(new_email);
+                } else {
+                    state.account.emails = vec![new_email];
+                    state.account.email = email;
+                }
+                None
+            }
+        };
+        state.inputs.push(input);
+        next
+    };
+    if next.is_none() {
+        state.flow_name = None;
+    }
+    state.pending_subtask = next.map(str::to_string);
+    let flow_token = state.next_id("flow");
+    state.flow_token = Some(flow_token.clone());
+    let subtasks: Vec<Value> = next.into_iter().map(subtask).collect();
+    Json(json!({ "flow_token": flow_token, "status": "success", "subtasks": subtasks }))
+        .into_response()
+}
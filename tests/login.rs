@@ -0,0 +1,780 @@
+mod common;
+
+use common::{
+    choose_method_subtask, password_script, subtask, two_factor_subtask, MockAccount, MockX,
+    TOTP_SECRET,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use serde_json::json;
+use x_rs::account::{
+    captcha::{CaptchaChallenge, CaptchaSolver},
+    flow::{FlowContext, Subtask, SubtaskHandler, SubtaskOutcome},
+    login::{LoginPrompt, LoginSession, LoginStep},
+    totp::{Algorithm, TotpConfig},
+    verification::{ChannelCodeProvider, EmailCodeProvider, MailboxCodeProvider},
+    Account, LoginCredentials, LoginError, XErrorCode,
+};
+
+#[tokio::test]
+async fn password_login_walks_the_subtasks_in_order() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login(None).login().await.unwrap();
+
+    assert_eq!(
+        mock.answered(),
+        [
+            "LoginJsInstrumentationSubtask",
+            "LoginEnterUserIdentifierSSO",
+            "LoginEnterPassword",
+            "LoginSuccessSubtask",
+        ]
+    );
+    let auth = serde_json::to_value(&auth).unwrap();
+    let ct0 = mock.state().ct0.clone().unwrap();
+    assert_eq!(auth["headers"]["x-csrf-token"], json!(ct0));
+    assert_eq!(
+        auth["headers"]["x-twitter-auth-type"],
+        json!("OAuth2Session")
+    );
+}
+
+#[tokio::test]
+async fn logged_in_auth_is_usable_by_account() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login_auth().await;
+    let account = Account::from_auth(auth, mock.endpoints()).unwrap();
+
+    let info = account.get_email_phone_info().await.unwrap();
+    assert_eq!(info.emails.len(), 1);
+    assert_eq!(info.emails[0].email, common::EMAIL);
+    let cookies: serde_json::Value = serde_json::from_str(&account.auth_cookie_string()).unwrap();
+    assert_eq!(
+        cookies["auth_token"].as_str(),
+        mock.state().auth_token.as_deref()
+    );
+}
+
+fn totp_script() -> Vec<serde_json::Value> {
+    vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        subtask("LoginTwoFactorAuthChallenge"),
+        subtask("LoginSuccessSubtask"),
+    ]
+}
+
+#[tokio::test]
+async fn totp_challenge_submits_current_code() {
+    let account = MockAccount {
+        totp_secret: Some(TOTP_SECRET.to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, totp_script()).await;
+    mock.login(Some(TOTP_SECRET)).login().await.unwrap();
+
+    assert_eq!(mock.answered()[3], "LoginTwoFactorAuthChallenge");
+}
+
+#[tokio::test]
+async fn authenticator_prompt_mentioning_phone_uses_totp() {
+    let account = MockAccount {
+        totp_secret: Some(TOTP_SECRET.to_string()),
+        ..Default::default()
+    };
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        two_factor_subtask("Open the authenticator app on your phone and enter the code."),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(account, script).await;
+    mock.login(Some(TOTP_SECRET)).login().await.unwrap();
+
+    assert_eq!(mock.state().used_totp_codes.len(), 1);
+}
+
+#[tokio::test]
+async fn totp_uri_settings_and_server_clock_are_used() {
+    let account = MockAccount {
+        totp_secret: Some(TOTP_SECRET.to_string()),
+        totp_algorithm: Algorithm::SHA256,
+        totp_digits: 8,
+        ..Default::default()
+    };
+    let mock = MockX::start(account, totp_script()).await;
+    mock.state().clock_offset = 600;
+    let uri = format!(
+        "otpauth://totp/X:{}?secret={}&issuer=X&algorithm=SHA256&digits=8&period=30",
+        common::USERNAME,
+        TOTP_SECRET.to_lowercase()
+    );
+    mock.login(Some(&uri)).login().await.unwrap();
+
+    let code = mock.state().inputs[3]["enter_text"]["text"].clone();
+    assert_eq!(code.as_str().unwrap().len(), 8);
+}
+
+#[tokio::test]
+async fn totp_waits_instead_of_reusing_a_code() {
+    let account = MockAccount {
+        totp_secret: Some(TOTP_SECRET.to_string()),
+        totp_period: 2,
+        ..Default::default()
+    };
+    let script = [totp_script(), totp_script(), totp_script()].concat();
+    let mock = MockX::start(account, script).await;
+    let config = TotpConfig::new(TOTP_SECRET)
+        .unwrap()
+        .with_period(2)
+        .unwrap();
+    let mut login = mock.login(None).with_totp(config.clone());
+    login.login().await.unwrap();
+    login.login().await.unwrap();
+    // A separate login from the same config, as when logging in again.
+    mock.login(None).with_totp(config).login().await.unwrap();
+
+    let used = mock.state().used_totp_codes.clone();
+    assert_eq!(used.len(), 3);
+}
+
+#[test]
+fn invalid_totp_secret_is_rejected() {
+    let error = LoginCredentials::username(common::USERNAME, common::PASSWORD)
+        .with_totp_secret("not base32!")
+        .unwrap_err();
+    assert!(
+        matches!(error, LoginError::InvalidTotpSecret { .. }),
+        "{error:?}"
+    );
+    assert!(matches!(
+        TotpConfig::new(TOTP_SECRET).unwrap().with_digits(10),
+        Err(LoginError::InvalidTotpSecret { .. })
+    ));
+}
+
+#[tokio::test]
+async fn backup_code_is_chosen_when_no_totp_secret() {
+    let account = MockAccount {
+        totp_secret: Some(TOTP_SECRET.to_string()),
+        backup_codes: vec!["a1b2c3d4e5f6".to_string(), "f6e5d4c3b2a1".to_string()],
+        ..Default::default()
+    };
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        two_factor_subtask("Use your code generator app to generate a code."),
+        choose_method_subtask(),
+        two_factor_subtask("Enter one of your backup codes."),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(account, script).await;
+    let mut login = mock
+        .login(None)
+        .with_backup_codes(["a1b2c3d4e5f6", "f6e5d4c3b2a1"]);
+    login.login().await.unwrap();
+
+    assert_eq!(login.used_backup_codes(), vec!["a1b2c3d4e5f6"]);
+    let state = mock.state();
+    assert_eq!(state.account.backup_codes, vec!["f6e5d4c3b2a1"]);
+    assert_eq!(
+        state.inputs[4]["choice_selection"]["selected_choices"],
+        json!(["2"])
+    );
+}
+
+#[tokio::test]
+async fn stepwise_login_reports_spent_backup_codes() {
+    let account = MockAccount {
+        backup_codes: vec!["a1b2c3d4e5f6".to_string()],
+        email_code: Some("4kkw8fcs".to_string()),
+        ..Default::default()
+    };
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        two_factor_subtask("Enter one of your backup codes."),
+        common::email_code_subtask(),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(account, script).await;
+    let login = mock.login(None).with_backup_codes(["a1b2c3d4e5f6"]);
+
+    let LoginStep::NeedsInput(pending) = login.start().await.unwrap() else {
+        panic!("login should wait for the email code");
+    };
+    assert_eq!(pending.used_backup_codes(), vec!["a1b2c3d4e5f6"]);
+    let LoginStep::Done {
+        used_backup_codes, ..
+    } = pending.submit("4kkw8fcs").await.unwrap()
+    else {
+        panic!("login should complete after the code");
+    };
+    assert_eq!(used_backup_codes, vec!["a1b2c3d4e5f6"]);
+    assert!(mock.state().account.backup_codes.is_empty());
+}
+
+#[tokio::test]
+async fn sms_challenge_uses_sms_code_provider() {
+    let account = MockAccount {
+        sms_code: Some("482913".to_string()),
+        ..Default::default()
+    };
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        two_factor_subtask("We sent a text message to your phone number ending in 42."),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(account, script).await;
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(matches!(error, LoginError::SmsCodeRequired), "{error:?}");
+
+    mock.state().script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        two_factor_subtask("We sent a text message to your phone number ending in 42."),
+        subtask("LoginSuccessSubtask"),
+    ]
+    .into();
+    let (sender, provider) = ChannelCodeProvider::new();
+    sender.send("482913".to_string()).unwrap();
+    mock.login(None)
+        .with_sms_code_provider(provider)
+        .login()
+        .await
+        .unwrap();
+    assert_eq!(mock.answered().last().unwrap(), "LoginSuccessSubtask");
+}
+
+#[tokio::test]
+async fn totp_challenge_without_secret_fails() {
+    let account = MockAccount {
+        totp_secret: Some(TOTP_SECRET.to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, totp_script()).await;
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(matches!(error, LoginError::TotpRequired), "{error:?}");
+    assert!(mock.state().auth_token.is_none());
+}
+
+#[tokio::test]
+async fn alternate_identifier_and_acid_send_email() {
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterAlternateIdentifierSubtask"),
+        subtask("LoginEnterPassword"),
+        subtask("LoginAcid"),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    mock.login(None).login().await.unwrap();
+
+    let inputs = mock.state().inputs.clone();
+    assert_eq!(inputs[2]["enter_text"]["text"], json!(common::EMAIL));
+    assert_eq!(inputs[4]["enter_text"]["text"], json!(common::EMAIL));
+}
+
+#[tokio::test]
+async fn alternate_identifier_follows_prompt() {
+    let account = MockAccount {
+        phone: Some(common::PHONE.to_string()),
+        ..Default::default()
+    };
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        json!({
+            "subtask_id": "LoginEnterAlternateIdentifierSubtask",
+            "enter_text": {
+                "primary_text": {"text": "Enter your phone number or username"},
+                "hint_text": "Phone or username",
+            },
+        }),
+        subtask("LoginEnterPassword"),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(account, script).await;
+    let credentials =
+        LoginCredentials::email(common::EMAIL, common::PASSWORD).with_username(common::USERNAME);
+    mock.login_with(credentials).login().await.unwrap();
+
+    let inputs = mock.state().inputs.clone();
+    let identifier =
+        &inputs[1]["settings_list"]["setting_responses"][0]["response_data"]["text_data"]["result"];
+    assert_eq!(identifier, &json!(common::EMAIL));
+    assert_eq!(inputs[2]["enter_text"]["text"], json!(common::USERNAME));
+}
+
+#[test]
+fn credentials_debug_hides_secrets() {
+    let credentials = LoginCredentials::phone(common::PHONE, common::PASSWORD)
+        .with_totp_secret(TOTP_SECRET)
+        .unwrap();
+    let debug = format!("{credentials:?}");
+    assert!(debug.contains(common::PHONE));
+    assert!(!debug.contains(common::PASSWORD));
+    assert!(!debug.contains(TOTP_SECRET));
+    assert!(debug.contains("<redacted>"));
+}
+
+#[tokio::test]
+async fn wrong_password_fails() {
+    let account = MockAccount {
+        password: "something else".to_string(),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, password_script()).await;
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(matches!(error, LoginError::WrongPassword), "{error:?}");
+    assert!(mock.state().auth_token.is_none());
+}
+
+#[tokio::test]
+async fn login_confirms_which_user_it_logged_into() {
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    let auth = mock.login(None).login().await.unwrap();
+    assert_eq!(auth.info().user_id.as_deref(), Some(common::USER_ID));
+    assert_eq!(auth.info().screen_name.as_deref(), Some(common::USERNAME));
+
+    // The check's own response rotates ct0; the returned auth has the new
+    // one.
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    mock.state().rotate_ct0_always = true;
+    let auth = mock.login(None).login().await.unwrap();
+    let ct0 = mock.state().ct0.clone();
+    let saved = serde_json::to_value(&auth).unwrap();
+    assert_eq!(saved["headers"]["x-csrf-token"].as_str(), ct0.as_deref());
+    mock.state().rotate_ct0_always = false;
+    let account = Account::from_auth(auth, mock.endpoints()).unwrap();
+    account.get_email_phone_info().await.unwrap();
+
+    let mock = MockX::start(MockAccount::default(), password_script()).await;
+    mock.state().verified_screen_name = Some("someone_else".to_string());
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(
+        matches!(&error, LoginError::WrongUser { actual, .. } if actual == "someone_else"),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn deny_login_subtask_fails() {
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        json!({
+            "subtask_id": "DenyLoginSubtask",
+            "cta": {"secondary_text": {"text": "We blocked an attempt to access your account."}},
+        }),
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    match mock.login(None).login().await.unwrap_err() {
+        LoginError::Denied { message } => {
+            assert_eq!(message, "We blocked an attempt to access your account.")
+        }
+        error => panic!("unexpected error: {error:?}"),
+    }
+    assert_eq!(mock.answered().last().unwrap(), "LoginEnterPassword");
+}
+
+#[tokio::test]
+async fn unknown_subtask_fails() {
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("SomeNewChallengeSubtask"),
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    match mock.login(None).login().await.unwrap_err() {
+        LoginError::UnknownSubtask { id, raw } => {
+            assert_eq!(id, "SomeNewChallengeSubtask");
+            assert_eq!(raw["subtask_id"], json!("SomeNewChallengeSubtask"));
+        }
+        error => panic!("unexpected error: {error:?}"),
+    }
+}
+
+fn email_code_script() -> Vec<serde_json::Value> {
+    vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        common::email_code_subtask(),
+        subtask("LoginSuccessSubtask"),
+    ]
+}
+
+#[tokio::test]
+async fn acid_code_comes_from_channel_provider() {
+    let account = MockAccount {
+        email_code: Some("4kkw8fcs".to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, email_code_script()).await;
+    let (sender, provider) = ChannelCodeProvider::new();
+    sender.send("4kkw8fcs".to_string()).unwrap();
+
+    let mut login = mock.login(None).with_email_code_provider(provider);
+    login.login().await.unwrap();
+    assert_eq!(
+        mock.state().inputs[3]["enter_text"]["text"],
+        json!("4kkw8fcs")
+    );
+}
+
+#[tokio::test]
+async fn acid_code_comes_from_maildir() {
+    let account = MockAccount {
+        email_code: Some("4kkw8fcs".to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, email_code_script()).await;
+    let maildir = std::env::temp_dir().join(format!("x-rs-maildir-{}", mock.addr.port()));
+    std::fs::create_dir_all(maildir.join("new")).unwrap();
+    std::fs::write(
+        maildir.join("new").join("1.eml"),
+        format!(
+            "From: X <info@x.com>\nTo: {}\nSubject: Your X confirmation code is 4kkw8fcs\n\nEnter it to log in.\n",
+            common::EMAIL
+        ),
+    )
+    .unwrap();
+
+    let provider = MailboxCodeProvider::maildir(&maildir).with_timeout(Duration::from_secs(1));
+    let result = mock
+        .login(None)
+        .with_email_code_provider(provider)
+        .login()
+        .await;
+    std::fs::remove_dir_all(&maildir).unwrap();
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn mbox_code_is_recent_and_from_x() {
+    let now = std::time::SystemTime::now();
+    let date = |ago: u64| httpdate::fmt_http_date(now - Duration::from_secs(ago));
+    let mbox = format!(
+        "From verify@x.com Mon Jan  1 00:00:00 2024\n\
+         From: X <verify@x.com>\n\
+         To: {email}\n\
+         Date: {}\n\
+         Subject: Your X confirmation code is 4kkw8fcs\n\
+         \n\
+         Enter it to log in.\n\
+         From someone@example.com Mon Jan  1 00:00:00 2024\n\
+         From: Someone <someone@example.com>\n\
+         To: {email}\n\
+         Date: {}\n\
+         \n\
+         Log in at x.com with 22222222\n\
+         From verify@x.com Mon Jan  1 00:00:00 2024\n\
+         From: X <verify@x.com>\n\
+         To: {email}\n\
+         Date: {}\n\
+         Subject: Your X confirmation code is 11111111\n",
+        date(60),
+        date(0),
+        date(3 * 24 * 60 * 60),
+        email = common::EMAIL,
+    );
+    let path = std::env::temp_dir().join(format!("x-rs-mbox-{}", std::process::id()));
+    std::fs::write(&path, mbox).unwrap();
+
+    let provider = MailboxCodeProvider::mbox(&path)
+        .with_timeout(Duration::from_millis(50))
+        .with_poll_interval(Duration::from_millis(10));
+    let first = provider.email_code(common::EMAIL).await;
+    // The only other X mail is days old, and the newest mail is not X's.
+    let second = provider.email_code(common::EMAIL).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(first.unwrap(), "4kkw8fcs");
+    assert!(second.is_err());
+}
+
+#[tokio::test]
+async fn shared_mailbox_hands_each_account_its_own_code() {
+    let now = std::time::SystemTime::now();
+    let date = |ago: u64| httpdate::fmt_http_date(now - Duration::from_secs(ago));
+    let mbox = format!(
+        "From verify@x.com Mon Jan  1 00:00:00 2024\n\
+         From: X <verify@x.com>\n\
+         To: Mock User <{email}>\n\
+         Date: {}\n\
+         Subject: Your X confirmation code is 4kkw8fcs\n\
+         \n\
+         From verify@x.com Mon Jan  1 00:00:00 2024\n\
+         From: X <verify@x.com>\n\
+         To: catchall@example.com\n\
+         Delivered-To: Other_User@example.com\n\
+         Date: {}\n\
+         Subject: Your X confirmation code is 9q8w7e6r\n",
+        date(60),
+        date(0),
+        email = common::EMAIL,
+    );
+    let path = std::env::temp_dir().join(format!("x-rs-shared-mbox-{}", std::process::id()));
+    std::fs::write(&path, mbox).unwrap();
+
+    let provider = MailboxCodeProvider::mbox(&path)
+        .with_timeout(Duration::from_millis(50))
+        .with_poll_interval(Duration::from_millis(10));
+    let mine = provider.email_code(&common::EMAIL.to_uppercase()).await;
+    let other = provider.email_code("other_user@example.com").await;
+    let stranger = provider.email_code("stranger@example.com").await;
+    std::fs::remove_file(&path).unwrap();
+    // The other account's code is newer but not addressed to this one.
+    assert_eq!(mine.unwrap(), "4kkw8fcs");
+    assert_eq!(other.unwrap(), "9q8w7e6r");
+    assert!(stranger.is_err());
+}
+
+#[tokio::test]
+async fn acid_code_without_provider_fails() {
+    let account = MockAccount {
+        email_code: Some("4kkw8fcs".to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, email_code_script()).await;
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(matches!(error, LoginError::EmailCodeRequired), "{error:?}");
+    assert!(mock.state().auth_token.is_none());
+}
+
+#[tokio::test]
+async fn x_error_codes_map_to_login_errors() {
+    let cases = [
+        (StatusCode::FORBIDDEN, 64, "Your account is suspended."),
+        (
+            StatusCode::FORBIDDEN,
+            326,
+            "Your account is temporarily locked.",
+        ),
+        (StatusCode::TOO_MANY_REQUESTS, 88, "Rate limit exceeded."),
+        (StatusCode::BAD_REQUEST, 366, "flow_token is invalid."),
+        // Not in answer to the password, so not a wrong password.
+        (
+            StatusCode::BAD_REQUEST,
+            399,
+            "Your password must be reset before logging in.",
+        ),
+    ];
+    for (status, code, message) in cases {
+        let mock = MockX::start(MockAccount::default(), password_script()).await;
+        mock.state().task_error = Some((status, code, message.to_string()));
+        let error = mock.login(None).login().await.unwrap_err();
+        match (code, error) {
+            (64, LoginError::Suspended)
+            | (326, LoginError::Locked)
+            | (399, LoginError::Denied { .. }) => {}
+            (88, LoginError::RateLimited { reset }) => assert_eq!(reset, Some(1700000000)),
+            (366, LoginError::Api(error)) => {
+                assert_eq!(error.status, 400);
+                assert_eq!(error.code, Some(XErrorCode::Other(366)));
+            }
+            (_, error) => panic!("unexpected error for code {code}: {error:?}"),
+        }
+    }
+}
+
+struct AcknowledgeHandler;
+
+#[async_trait]
+impl SubtaskHandler for AcknowledgeHandler {
+    async fn handle(
+        &self,
+        subtask: &Subtask,
+        _context: &mut FlowContext,
+    ) -> Result<SubtaskOutcome, LoginError> {
+        Ok(SubtaskOutcome::Submit(vec![json!({
+            "subtask_id": subtask.subtask_id,
+            "cta": {"link": "next_link"},
+        })]))
+    }
+}
+
+#[tokio::test]
+async fn registered_handler_answers_new_subtask() {
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("SomeNewChallengeSubtask"),
+        subtask("LoginEnterPassword"),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    mock.login(None)
+        .with_subtask_handler("SomeNewChallengeSubtask", AcknowledgeHandler)
+        .login()
+        .await
+        .unwrap();
+
+    assert_eq!(mock.answered()[2], "SomeNewChallengeSubtask");
+    assert_eq!(mock.state().inputs[2]["cta"]["link"], json!("next_link"));
+}
+
+struct PausingHandler;
+
+#[async_trait]
+impl SubtaskHandler for PausingHandler {
+    async fn handle(
+        &self,
+        _subtask: &Subtask,
+        _context: &mut FlowContext,
+    ) -> Result<SubtaskOutcome, LoginError> {
+        Ok(SubtaskOutcome::Pause)
+    }
+}
+
+#[tokio::test]
+async fn paused_subtasks_report_what_they_need() {
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("SomeNewChallengeSubtask"),
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    let error = mock
+        .login(None)
+        .with_subtask_handler("SomeNewChallengeSubtask", PausingHandler)
+        .login()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, LoginError::InputRequired { id, .. } if id == "SomeNewChallengeSubtask"),
+        "{error:?}"
+    );
+
+    // Only text messages are offered, and no SMS code provider is set.
+    let mut choose_method = choose_method_subtask();
+    choose_method["choice_selection"]["choices"] =
+        json!([{"id": "1", "text": {"text": "Text message"}}]);
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        choose_method,
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(matches!(error, LoginError::SmsCodeRequired), "{error:?}");
+}
+
+#[tokio::test]
+async fn acid_without_email_asks_for_the_address() {
+    let confirm_email = json!({
+        "subtask_id": "LoginAcid",
+        "enter_text": {
+            "primary_text": {"text": "Confirm your email address"},
+            "hint_text": "Email",
+        },
+    });
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        subtask("LoginEnterPassword"),
+        confirm_email,
+        subtask("LoginSuccessSubtask"),
+    ];
+    let credentials = || LoginCredentials::username(common::USERNAME, common::PASSWORD);
+    let mock = MockX::start(MockAccount::default(), script.clone()).await;
+    let error = mock.login_with(credentials()).login().await.unwrap_err();
+    assert!(
+        matches!(&error, LoginError::IdentifierRequired { prompt } if prompt.contains("email address")),
+        "{error:?}"
+    );
+
+    let mock = MockX::start(MockAccount::default(), script).await;
+    let LoginStep::NeedsInput(pending) = mock.login_with(credentials()).start().await.unwrap()
+    else {
+        panic!("login should wait for the email address");
+    };
+    assert_eq!(
+        pending.prompt(),
+        LoginPrompt::Identifier {
+            prompt: "Confirm your email address Email".to_string()
+        }
+    );
+    let LoginStep::Done { .. } = pending.submit(common::EMAIL).await.unwrap() else {
+        panic!("login should complete after the address");
+    };
+}
+
+struct ScriptedSolver(Arc<Mutex<Vec<CaptchaChallenge>>>);
+
+#[async_trait]
+impl CaptchaSolver for ScriptedSolver {
+    async fn solve(&self, challenge: &CaptchaChallenge) -> eyre::Result<String> {
+        self.0.lock().unwrap().push(challenge.clone());
+        Ok("arkose-token".to_string())
+    }
+}
+
+#[tokio::test]
+async fn arkose_challenge_is_passed_to_solver() {
+    let account = MockAccount {
+        captcha_token: Some("arkose-token".to_string()),
+        ..Default::default()
+    };
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        common::arkose_subtask(),
+        subtask("LoginEnterPassword"),
+        subtask("LoginSuccessSubtask"),
+    ];
+    let mock = MockX::start(account, script).await;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    mock.login(None)
+        .with_captcha_solver(ScriptedSolver(seen.clone()))
+        .login()
+        .await
+        .unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[0].public_key, "2F4F0B28-BC94-4271-8AD7-A51662E3C91C");
+    assert_eq!(seen[0].blob.as_deref(), Some("blob-123"));
+}
+
+#[tokio::test]
+async fn arkose_challenge_without_solver_fails() {
+    let script = vec![
+        subtask("LoginEnterUserIdentifierSSO"),
+        common::arkose_subtask(),
+    ];
+    let mock = MockX::start(MockAccount::default(), script).await;
+    let error = mock.login(None).login().await.unwrap_err();
+    assert!(matches!(error, LoginError::CaptchaRequired), "{error:?}");
+}
+
+#[tokio::test]
+async fn paused_login_resumes_from_saved_session() {
+    let account = MockAccount {
+        email_code: Some("4kkw8fcs".to_string()),
+        ..Default::default()
+    };
+    let mock = MockX::start(account, email_code_script()).await;
+
+    let LoginStep::NeedsInput(pending) = mock.login(None).start().await.unwrap() else {
+        panic!("login should wait for the email code");
+    };
+    assert_eq!(
+        pending.prompt(),
+        LoginPrompt::EmailCode {
+            email: common::EMAIL.to_string()
+        }
+    );
+    let saved = serde_json::to_string(&pending.session()).unwrap();
+    drop(pending);
+
+    let session: LoginSession = serde_json::from_str(&saved).unwrap();
+    let pending = mock.login(None).resume(session).unwrap();
+    let LoginStep::Done { auth, .. } = pending.submit("4kkw8fcs").await.unwrap() else {
+        panic!("login should complete after the code");
+    };
+    assert_eq!(mock.answered().last().unwrap(), "LoginSuccessSubtask");
+    Account::from_auth(auth, mock.endpoints())
+        .unwrap()
+        .get_email_phone_info()
+        .await
+        .unwrap();
+}